@@ -0,0 +1,59 @@
+//! 通过 beelog::client 在多个节点执行命令
+//!
+//! ```shell
+//! JUMP_PASSWORD=xxx cargo run --example exec -- jump.example.com 2222 ops "uptime" app-01 app-02
+//! ```
+
+use std::sync::Arc;
+use beelog::client::Client;
+use beelog::config::{AuthMethod, ServerInfo};
+use beelog::jump_server_helper::ExecStatus;
+use beelog::mfa::TotpProvider;
+use futures::StreamExt;
+
+#[tokio::main]
+async fn main() -> beelog::error::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 5 {
+        eprintln!("用法: exec <host> <port> <user> <command> <node>...");
+        std::process::exit(2);
+    }
+    let port = args[1].parse().unwrap_or(22);
+    let mut server = ServerInfo::new(&args[0], port, &args[2]);
+    server.password = Some("env:JUMP_PASSWORD".to_string());
+    server.auth_methods = vec![AuthMethod::Agent, AuthMethod::Password, AuthMethod::KeyboardInteractive];
+
+    let mut builder = Client::builder()
+        .server(server)
+        .nodes(&args[4..])
+        .concurrency(8)
+        .command_timeout(60)
+        .on_connect(|node, error| match error {
+            Some(e) => eprintln!("{} 连接失败: {}", node, e),
+            None => eprintln!("{} 已连接", node),
+        })
+        .on_output(|node, chunk| {
+            for line in chunk.lines().filter(|l| !l.trim().is_empty()) {
+                println!("{}: {}", node, line.trim_end());
+            }
+        });
+    if let Ok(secret) = std::env::var("JUMP_MFA_SECRET") {
+        builder = builder.mfa_provider(Arc::new(TotpProvider::new(secret)?));
+    }
+    let client = builder.connect().await?;
+
+    let mut results = client.exec(&args[3]);
+    while let Some(result) = results.next().await {
+        match result.result {
+            Ok(output) if output.status == ExecStatus::Completed => eprintln!("{} 完成", result.node),
+            Ok(output) => eprintln!("{} 未完成: {:?}", result.node, output.status),
+            Err(e) => eprintln!("{} 执行失败: {}", result.node, e),
+        }
+    }
+    drop(results);
+
+    for (node, e) in client.close().await {
+        eprintln!("{} 关闭失败: {}", node, e);
+    }
+    Ok(())
+}
@@ -1,4 +1,8 @@
-use clap::Parser;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use std::path::PathBuf;
+use crate::config::OutputFormat;
+use crate::tr;
 
 /// 收集jumpserver日志
 #[derive(Parser, Debug)]
@@ -11,9 +15,202 @@ pub struct Args {
     /// 指定节点分组配置name
     #[arg(short, long)]
     pub node_group: Option<String>,
+
+    /// 同时连接的节点数上限
+    #[arg(short, long)]
+    pub parallel: Option<usize>,
+
+    /// 按完成顺序输出各节点结果，默认全部结束后按节点顺序输出
+    #[arg(short, long)]
+    pub first_come: bool,
+
+    /// 输出格式：banner 按节点分块，prefix 每行添加节点前缀
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// 按标签选择节点，如 'role=web and dc!=bj'，未指定节点组时从服务器的所有节点中选择
+    #[arg(long)]
+    pub select: Option<String>,
+
+    /// 配置文件路径，默认读取 BEELOG_CONFIG 或 ~/.config/beelog/config.toml
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// 输出调试日志，-v 记录连接及认证过程，-vv 额外记录收到的原始输出
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// 调试日志写入文件而不是标准错误，未指定 -v 时按 -v 记录
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
+/// 子命令，未指定时连接节点进入交互
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 管理配置文件
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// 将外部清单中的 server 及 node group 写入配置文件
+    Import {
+        /// 清单路径，未指定时使用配置中的 inventory；ansible: / ssh: 前缀指定类型
+        sources: Vec<String>,
+        /// 只显示将导入的内容，不写入配置文件
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 管理配置文件中的节点组
+    #[command(subcommand)]
+    Group(GroupCommand),
+    /// 通过JumpServer菜单列出可访问的资产
+    Discover {
+        /// 指定server配置name，未指定时使用默认服务器
+        #[arg(short, long)]
+        server: Option<String>,
+        /// 搜索关键字，未指定时列出所有资产
+        filter: Option<String>,
+        /// 将列出的资产保存为新的节点组
+        #[arg(long, value_name = "GROUP")]
+        save: Option<String>,
+    },
+}
+
+/// 配置文件管理
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// 交互式生成配置文件
+    Init {
+        /// 覆盖已存在的配置文件
+        #[arg(long)]
+        force: bool,
+    },
+    /// 检查配置文件
+    Check,
+    /// 显示合并后的有效配置，敏感字段已隐藏
+    Show,
+}
+
+
+/// 节点组管理，保留配置文件的注释及格式
+#[derive(Subcommand, Debug)]
+pub enum GroupCommand {
+    /// 添加节点组
+    Add {
+        /// 节点组名称
+        name: String,
+        /// 节点，支持范围及逗号分隔
+        #[arg(required = true)]
+        nodes: Vec<String>,
+        /// 适用的服务器名称，可多次指定
+        #[arg(short, long)]
+        server: Vec<String>,
+        /// 覆盖已存在的节点组
+        #[arg(long)]
+        force: bool,
+    },
+    /// 删除配置文件中的节点组
+    Remove {
+        /// 节点组名称
+        name: String,
+    },
+}
+
+
+/// 输出格式的可选值，说明按当前语言显示
+fn format_parser() -> impl TypedValueParser<Value = OutputFormat> {
+    PossibleValuesParser::new([
+        PossibleValue::new("banner").help(tr!(CliFormatBanner)),
+        PossibleValue::new("prefix").help(tr!(CliFormatPrefix)),
+    ]).map(|value| OutputFormat::from_str(&value, true).unwrap_or_default())
+}
+
+/**
+ * 预先从命令行中取出 --config，用于在生成帮助信息前按配置设置语言
+ * 忽略其他参数的错误，完整的校验由 init 完成
+ */
+pub fn config_arg() -> Option<PathBuf> {
+    Args::command()
+        .ignore_errors(true)
+        .disable_help_flag(true)
+        .disable_version_flag(true)
+        .disable_help_subcommand(true)
+        .try_get_matches()
+        .ok()
+        .and_then(|matches| matches.get_one::<PathBuf>("config").cloned())
+}
 
+/**
+ * 解析命令行参数
+ * 帮助信息按当前语言输出，需在调用前完成语言设置
+ */
 pub fn init() -> Args {
-    Args::parse()
+    // global 参数在 build 时复制到子命令，子命令的参数在 build 后修改会丢失已生成的设置，需在此之前修改说明
+    let mut command = Args::command()
+        .about(tr!(CliAbout))
+        .mut_arg("server", |arg| arg.help(tr!(CliServer)))
+        .mut_arg("node_group", |arg| arg.help(tr!(CliNodeGroup)))
+        .mut_arg("parallel", |arg| arg.help(tr!(CliParallel)))
+        .mut_arg("first_come", |arg| arg.help(tr!(CliFirstCome)))
+        .mut_arg("format", |arg| arg.help(tr!(CliFormat)).value_parser(format_parser()))
+        .mut_arg("select", |arg| arg.help(tr!(CliSelect)))
+        .mut_arg("config", |arg| arg.help(tr!(CliConfig)))
+        .mut_arg("verbose", |arg| arg.help(tr!(CliVerbose)))
+        .mut_arg("log_file", |arg| arg.help(tr!(CliLogFile)))
+        .mut_subcommand("config", |config| config
+            .mut_subcommand("init", |init| init
+                .mut_arg("force", |arg| arg.help(tr!(CliConfigInitForce)))))
+        .mut_subcommand("import", |import| import
+            .mut_arg("sources", |arg| arg.help(tr!(CliImportSources)))
+            .mut_arg("dry_run", |arg| arg.help(tr!(CliImportDryRun))))
+        .mut_subcommand("group", |group| group
+            .mut_subcommand("add", |add| add
+                .mut_arg("name", |arg| arg.help(tr!(CliGroupName)))
+                .mut_arg("nodes", |arg| arg.help(tr!(CliGroupNodes)))
+                .mut_arg("server", |arg| arg.help(tr!(CliGroupServer)))
+                .mut_arg("force", |arg| arg.help(tr!(CliGroupForce))))
+            .mut_subcommand("remove", |remove| remove
+                .mut_arg("name", |arg| arg.help(tr!(CliGroupName)))))
+        .mut_subcommand("discover", |discover| discover
+            .mut_arg("server", |arg| arg.help(tr!(CliDiscoverServer)))
+            .mut_arg("filter", |arg| arg.help(tr!(CliDiscoverFilter)))
+            .mut_arg("save", |arg| arg.help(tr!(CliDiscoverSave))));
+    // 生成内置的 help / version 参数后才能修改其说明
+    command.build();
+    let command = command
+        .mut_arg("help", |arg| arg.help(tr!(CliHelp)))
+        .mut_arg("version", |arg| arg.help(tr!(CliVersion)))
+        .mut_subcommand("config", |config| config
+            .about(tr!(CliConfigCommand))
+            .mut_arg("help", |arg| arg.help(tr!(CliHelp)))
+            .mut_subcommand("init", |init| init
+                .about(tr!(CliConfigInit))
+                .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+            .mut_subcommand("check", |check| check
+                .about(tr!(CliConfigCheck))
+                .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+            .mut_subcommand("show", |show| show
+                .about(tr!(CliConfigShow))
+                .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+            .mut_subcommand("help", |help| help.about(tr!(CliHelpCommand))))
+        .mut_subcommand("import", |import| import
+            .about(tr!(CliImport))
+            .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+        .mut_subcommand("group", |group| group
+            .about(tr!(CliGroupCommand))
+            .mut_arg("help", |arg| arg.help(tr!(CliHelp)))
+            .mut_subcommand("add", |add| add
+                .about(tr!(CliGroupAdd))
+                .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+            .mut_subcommand("remove", |remove| remove
+                .about(tr!(CliGroupRemove))
+                .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+            .mut_subcommand("help", |help| help.about(tr!(CliHelpCommand))))
+        .mut_subcommand("discover", |discover| discover
+            .about(tr!(CliDiscover))
+            .mut_arg("help", |arg| arg.help(tr!(CliHelp))))
+        .mut_subcommand("help", |help| help.about(tr!(CliHelpCommand)));
+    Args::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit())
 }
\ No newline at end of file
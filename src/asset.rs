@@ -0,0 +1,136 @@
+//! JumpServer 资产列表解析
+//!
+//! 菜单中输入 `p` 或 `/关键字` 后按页输出资产表格：
+//! ```text
+//!   ID  | Hostname     | IP          | Comment
+//! +-----+--------------+-------------+---------+
+//!   1   | app-01       | 10.0.0.1    | web
+//! Page: 1, Count: 10, Total Page: 2, Total Count: 15
+//! ```
+//! 表头按名称识别各列，兼容中文界面及新版的 Name / Address 列。
+
+/// 资产
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Asset {
+    pub id: String,
+    /// 主机名，即菜单中进入资产时输入的名称
+    pub hostname: String,
+    pub ip: String,
+    pub comment: String,
+}
+
+/// 单页的资产及页码
+#[derive(Debug, Default, PartialEq)]
+pub struct AssetPage {
+    pub assets: Vec<Asset>,
+    /// 当前页及总页数，未输出分页信息时为 None
+    pub page: Option<(u32, u32)>,
+}
+
+impl AssetPage {
+
+    /// 是否还有下一页
+    pub fn has_next(&self) -> bool {
+        self.page.is_some_and(|(page, total)| page < total)
+    }
+}
+
+/// 各列的表头名称
+const ID_HEADERS: &[&str] = &["id"];
+const HOSTNAME_HEADERS: &[&str] = &["hostname", "name", "主机名", "名称"];
+const IP_HEADERS: &[&str] = &["ip", "address", "地址"];
+const COMMENT_HEADERS: &[&str] = &["comment", "备注"];
+/// 分页信息行的开头
+const PAGE_MARKS: &[&str] = &["Page:", "页码："];
+
+/**
+ * 解析一页输出
+ * 第一条包含 ID 列的表格行为表头，之后 ID 列为数字的行为资产
+ * 分页信息行中依次为当前页、每页数量、总页数、总数量
+ */
+pub fn parse_page(output: &str) -> AssetPage {
+    let output = strip_ansi(output);
+    let mut columns: Option<[Option<usize>; 4]> = None;
+    let mut page = AssetPage::default();
+    for line in output.lines().map(str::trim) {
+        if PAGE_MARKS.iter().any(|mark| line.starts_with(mark)) {
+            let numbers: Vec<u32> = line.split(|c: char| !c.is_ascii_digit())
+                .filter_map(|n| n.parse().ok())
+                .collect();
+            if let [current, _, total, ..] = numbers[..] {
+                page.page = Some((current, total));
+            }
+            continue;
+        }
+        if !line.contains('|') {
+            continue;
+        }
+        let cells: Vec<&str> = line.split('|').map(str::trim).collect();
+        match columns {
+            None => {
+                let find = |names: &[&str]| cells.iter().position(|cell| names.iter().any(|name| cell.eq_ignore_ascii_case(name)));
+                if let Some(id) = find(ID_HEADERS) {
+                    columns = Some([Some(id), find(HOSTNAME_HEADERS), find(IP_HEADERS), find(COMMENT_HEADERS)]);
+                }
+            }
+            Some([id, hostname, ip, comment]) => {
+                let cell = |index: Option<usize>| index.and_then(|i| cells.get(i)).copied().unwrap_or_default().to_string();
+                let id = cell(id);
+                let hostname = cell(hostname);
+                if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) || hostname.is_empty() {
+                    continue;
+                }
+                page.assets.push(Asset { id, hostname, ip: cell(ip), comment: cell(comment) });
+            }
+        }
+    }
+    page
+}
+
+/// 去除终端颜色等 ANSI 转义序列
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        if chars.next_if_eq(&'[').is_some() {
+            // CSI 序列以 0x40-0x7e 范围内的字符结束
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            chars.next();
+        }
+    }
+    stripped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_page() {
+        let page = parse_page("p\r\n  \x1b[32mID\x1b[0m  | Hostname | IP       | Comment\r\n\
+            +-----+----------+----------+---------+\r\n\
+              1   | app-01   | 10.0.0.1 | web\r\n\
+              2   | app-02   | 10.0.0.2 |\r\n\
+            Page: 1, Count: 2, Total Page: 3, Total Count: 5\r\n[Host]> ");
+        assert_eq!(page.assets.len(), 2);
+        assert_eq!(page.assets[0], Asset { id: "1".into(), hostname: "app-01".into(), ip: "10.0.0.1".into(), comment: "web".into() });
+        assert_eq!(page.page, Some((1, 3)));
+        assert!(page.has_next());
+
+        let page = parse_page("  ID | 名称 | 地址 | 平台 | 备注\n  7 | db-1 | 10.0.1.7 | Linux | 主库\n页码：2，每页行数：10，总页数：2，总数量：11\n");
+        assert_eq!(page.assets[0].hostname, "db-1");
+        assert_eq!(page.assets[0].comment, "主库");
+        assert!(!page.has_next());
+        assert_eq!(parse_page("No asset matched\r\nOpt> "), AssetPage::default());
+    }
+}
@@ -0,0 +1,261 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ssh2::{KeyboardInteractivePrompt, Session};
+use std::sync::{Arc, Mutex};
+use crate::config::{self, AuthMethod, ServerInfo};
+use crate::error::{BeelogError, Result};
+use crate::interaction::Interaction;
+use crate::mfa::{MfaProvider, TotpProvider};
+use crate::redact;
+use crate::tr;
+
+
+const MFA_MARK : &str = "OTP Code";
+const PASSWORD_MARK : &str = "password";
+const OPENSSH_KEY_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+/// 认证凭据
+/// 在连接前统一准备，私钥密码只提示一次，所有节点共用
+#[derive(Clone, Default)]
+pub struct Credentials {
+    key_path: Option<PathBuf>,
+    passphrase: Option<String>,
+    password: Option<String>,
+    mfa: Option<Arc<dyn MfaProvider>>,
+}
+
+impl Credentials {
+
+    /// 解析配置中的凭据，私钥加密且未配置密码时通过 interaction 输入
+    pub fn prepare(server_info: &ServerInfo, interaction: &dyn Interaction) -> Result<Self> {
+        let key_path = server_info.key_path.as_deref().map(config::expand_path);
+        let uses = |method: AuthMethod| server_info.auth_methods.contains(&method);
+
+        let mut passphrase = match &server_info.key_passphrase {
+            Some(source) => Some(resolve_secret(source)?),
+            None => None,
+        };
+        if passphrase.is_none() && uses(AuthMethod::Publickey)
+            && let Some(path) = &key_path
+            && is_key_encrypted(path)? {
+            let input = interaction.key_passphrase(path)
+                .ok_or_else(|| BeelogError::Auth(tr!(AuthNoPassphrase, path.display())))?;
+            passphrase = Some(input);
+        }
+        let password = match &server_info.password {
+            Some(source) if uses(AuthMethod::Password) || uses(AuthMethod::KeyboardInteractive) => Some(resolve_secret(source)?),
+            _ => None,
+        };
+        for secret in passphrase.iter().chain(password.iter()).chain(server_info.secret_code.iter()) {
+            redact::register(secret);
+        }
+        let mfa = match &server_info.secret_code {
+            Some(secret_code) => Some(Arc::new(TotpProvider::new(secret_code.as_str())?) as Arc<dyn MfaProvider>),
+            None => None,
+        };
+        Ok(Credentials {
+            key_path,
+            passphrase,
+            password,
+            mfa,
+        })
+    }
+
+    /// 替换 MFA 动态码来源，优先于配置中的 secret_code
+    pub fn with_mfa(mut self, provider: Arc<dyn MfaProvider>) -> Self {
+        self.mfa = Some(provider);
+        self
+    }
+}
+
+/// 键盘交互结构，应答MFA及密码提示，记录无法应答的提示
+struct InteractivePrompt<'a> {
+    credentials: &'a Credentials,
+    unknown: Mutex<Vec<String>>,
+}
+
+/// 键盘交互实现
+impl KeyboardInteractivePrompt for InteractivePrompt<'_> {
+    fn prompt(
+        &mut self,
+        _username: &str,
+        instructions: &str,
+        prompts: &[ssh2::Prompt<'_>]
+    ) -> Vec<String> {
+        tracing::debug!(instructions, prompts = prompts.len(), "键盘交互认证");
+        let mut responses = Vec::new();
+        for prompt in prompts {
+            tracing::debug!(text = %prompt.text, echo = prompt.echo, "认证提示");
+            let text = prompt.text.to_lowercase();
+            if let (true, Some(mfa)) = (prompt.text.contains(MFA_MARK), &self.credentials.mfa) {
+                // 动态码每次不同，只用于本次应答且不写入日志，无需登记
+                let code = mfa.code().unwrap_or_else(|e| {
+                    self.unknown.lock().unwrap_or_else(|e| e.into_inner()).push(format!("{} ({})", prompt.text, e));
+                    String::new()
+                });
+                responses.push(code);
+            } else if let (true, Some(password)) = (text.contains(PASSWORD_MARK), &self.credentials.password) {
+                responses.push(password.clone());
+            } else {
+                self.unknown.lock().unwrap_or_else(|e| e.into_inner()).push(prompt.text.to_string());
+                responses.push(String::new());
+            }
+        }
+        responses
+    }
+}
+
+/**
+ * 按配置顺序尝试认证
+ * 某种方式失败（或仅部分成功）时继续尝试下一种，全部尝试后仍未认证则返回各方式的失败原因
+ */
+pub fn authenticate(sess: &Session, server_info: &ServerInfo, credentials: &Credentials) -> Result<()> {
+    let user = server_info.user.as_str();
+    let mut failures = Vec::new();
+    for method in &server_info.auth_methods {
+        if sess.authenticated() {
+            break;
+        }
+        tracing::debug!(?method, user, "尝试认证");
+        let started = Instant::now();
+        let res = match method {
+            AuthMethod::Agent => auth_agent(sess, user),
+            AuthMethod::Publickey => match &credentials.key_path {
+                Some(path) => sess.userauth_pubkey_file(user, None, path, credentials.passphrase.as_deref())
+                    .map_err(|e| tr!(AuthPublickeyFailed, e)),
+                None => Err(tr!(AuthNoKeyPath)),
+            },
+            AuthMethod::Password => match &credentials.password {
+                Some(password) => sess.userauth_password(user, password)
+                    .map_err(|e| tr!(AuthPasswordFailed, e)),
+                None => Err(tr!(AuthNoPassword)),
+            },
+            AuthMethod::KeyboardInteractive => {
+                let mut prompt = InteractivePrompt { credentials, unknown: Mutex::new(Vec::new()) };
+                let res = sess.userauth_keyboard_interactive(user, &mut prompt);
+                let unknown = prompt.unknown.into_inner().unwrap_or_else(|e| e.into_inner());
+                res.map_err(|e| match unknown.is_empty() {
+                    true => tr!(AuthInteractiveFailed, e),
+                    false => tr!(AuthInteractiveUnanswered, e, unknown.join(", ")),
+                })
+            },
+        };
+        match res {
+            Ok(()) => tracing::debug!(?method, elapsed = ?started.elapsed(), authenticated = sess.authenticated(), "认证结束"),
+            Err(e) => {
+                tracing::debug!(?method, elapsed = ?started.elapsed(), error = %e, "认证失败");
+                failures.push(e);
+            }
+        }
+    }
+    if !sess.authenticated() {
+        return Err(BeelogError::Auth(failures.join("; ")));
+    }
+    Ok(())
+}
+
+/// 使用 ssh-agent 中的身份逐个尝试
+fn auth_agent(sess: &Session, user: &str) -> Result<(), String> {
+    let mut agent = sess.agent().map_err(|e| tr!(AgentInitFailed, e))?;
+    agent.connect().map_err(|e| tr!(AgentConnectFailed, e))?;
+    agent.list_identities().map_err(|e| tr!(AgentListFailed, e))?;
+    let identities = agent.identities().map_err(|e| tr!(AgentListFailed, e))?;
+    let res = identities.iter()
+        .find(|identity| agent.userauth(user, identity).is_ok())
+        .map(|_| ())
+        .ok_or_else(|| tr!(AgentNoIdentity));
+    let _ = agent.disconnect();
+    res
+}
+
+/**
+ * 解析密钥来源
+ *      env:NAME    读取环境变量
+ *      file:path   读取文件内容
+ *      cmd:command 执行本地命令并读取标准输出
+ *      其他        作为明文
+ */
+pub fn resolve_secret(source: &str) -> Result<String> {
+    resolve_secret_with(source, |name| std::env::var(name))
+}
+
+/// 按指定的方式读取环境变量，测试时无需修改进程的环境变量
+fn resolve_secret_with(source: &str, var: impl Fn(&str) -> std::result::Result<String, std::env::VarError>) -> Result<String> {
+    if let Some(name) = source.strip_prefix("env:") {
+        var(name).map_err(|e| BeelogError::Config(tr!(SecretEnvFailed, name, e)))
+    } else if let Some(path) = source.strip_prefix("file:") {
+        let path = config::expand_path(path);
+        fs::read_to_string(&path)
+            .map(|content| content.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| BeelogError::Config(tr!(SecretFileFailed, path.display(), e)))
+    } else if let Some(command) = source.strip_prefix("cmd:") {
+        let output = shell_command(command).output().map_err(|e| BeelogError::Config(tr!(SecretCmdFailed, e)))?;
+        if !output.status.success() {
+            return Err(BeelogError::Config(tr!(SecretCmdFailed, output.status)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']).to_string())
+    } else {
+        Ok(source.to_string())
+    }
+}
+
+/// 通过系统shell执行命令
+pub fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/**
+ * 判断私钥是否加密
+ * PEM 格式通过 ENCRYPTED 标记判断，OpenSSH 格式读取加密算法名称
+ */
+fn is_key_encrypted(path: &Path) -> Result<bool> {
+    let content = fs::read_to_string(path).map_err(|e| BeelogError::Config(tr!(KeyReadFailed, path.display(), e)))?;
+    if content.contains("ENCRYPTED") {
+        return Ok(true);
+    }
+    if !content.contains("BEGIN OPENSSH PRIVATE KEY") {
+        return Ok(false);
+    }
+    let body: String = content.lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let bytes = STANDARD.decode(body.trim()).map_err(|e| BeelogError::Decode(tr!(KeyInvalid, path.display(), e)))?;
+    let Some(rest) = bytes.strip_prefix(OPENSSH_KEY_MAGIC) else {
+        return Ok(false);
+    };
+    if rest.len() < 4 {
+        return Ok(false);
+    }
+    let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+    let cipher = rest.get(4..4 + len).unwrap_or_default();
+    Ok(cipher != b"none")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secret() {
+        let var = |name: &str| match name {
+            "SECRET" => Ok("s3cret".to_string()),
+            _ => Err(std::env::VarError::NotPresent),
+        };
+        assert_eq!(resolve_secret_with("env:SECRET", var).unwrap(), "s3cret");
+        assert_eq!(resolve_secret_with("plain", var).unwrap(), "plain");
+        assert!(resolve_secret_with("env:UNDEFINED", var).is_err());
+    }
+}
@@ -1,4 +1,6 @@
-use beelog::config;
+use beelog::config::{self, OutputFormat};
+use beelog::tr;
+use clap::ValueEnum;
 use chrono::Local;
 use reedline::{FileBackedHistory, Prompt, PromptEditMode, PromptHistorySearch, PromptHistorySearchStatus, Reedline};
 use std::borrow::Cow;
@@ -6,6 +8,8 @@ use std::borrow::Cow;
 // 禁止阻塞命令
 /// 不支持的命令
 const BLOCKED_COMMANDS: &[&str] = &["tail", "less", "top", "vi", "watch", "more", "htop", "nano"];
+/// 保留的历史命令条数
+const HISTORY_CAPACITY: usize = 50;
 
 
 pub struct CliLine {
@@ -15,16 +19,19 @@ pub struct CliLine {
 
 impl CliLine {
 
-    pub fn new(left_prompt: &String) -> Self {
-        let history_path = config::get_history_path();
-        let history = Box::new(
-            FileBackedHistory::with_file(50, history_path.into())
-                .map_err(|e| format!("历史文件读取异常: {}", e))
-                .unwrap(),
-        );
+    pub fn new(left_prompt: &str) -> Self {
+        // 历史文件不可用时只保留本次会话的历史
+        let history = config::get_history_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| FileBackedHistory::with_file(HISTORY_CAPACITY, path).map_err(|e| tr!(HistoryReadFailed, e)))
+            .unwrap_or_else(|e| {
+                println!("⚠️ {}", e);
+                FileBackedHistory::new(HISTORY_CAPACITY).expect("history capacity")
+            });
+        let history = Box::new(history);
         let line_editor = Reedline::create()
             .with_history(history);
-        let prompt = CustomPrompt::new(left_prompt.clone());
+        let prompt = CustomPrompt::new(left_prompt.to_string());
         Self {
             line_editor,
             prompt,
@@ -44,39 +51,112 @@ impl CustomPrompt {
         }
     }
 
+    /// 修改左侧提示，如切换节点后显示新的节点组
+    pub fn set_left_prompt(&mut self, left_prompt: String) {
+        self.left_prompt = left_prompt;
+    }
 }
 
 impl Prompt for CustomPrompt {
 
-    fn render_prompt_left(&self) -> Cow<str> {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.left_prompt)
     }
 
-    fn render_prompt_right(&self) -> Cow<str> {
+    fn render_prompt_right(&self) -> Cow<'_, str> {
         Cow::Owned(get_now())
     }
 
-    fn render_prompt_indicator(&self, _: PromptEditMode) -> Cow<str> {
+    fn render_prompt_indicator(&self, _: PromptEditMode) -> Cow<'_, str> {
         Cow::Borrowed(" >> ")
     }
 
-    fn render_prompt_multiline_indicator(&self) -> Cow<str> {
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
         Cow::Borrowed("render_prompt_multiline_indicator")
     }
 
-    fn render_prompt_history_search_indicator(&self, history_search: PromptHistorySearch) -> Cow<str> {
+    fn render_prompt_history_search_indicator(&self, history_search: PromptHistorySearch) -> Cow<'_, str> {
         let prefix = match history_search.status {
             PromptHistorySearchStatus::Passing => "",
             PromptHistorySearchStatus::Failing => "❌️ ",
         };
-        Cow::Owned(format!(
-            " ({}搜索: {}) ",
-            prefix, history_search.term
-        ))
+        Cow::Owned(tr!(HistorySearch, prefix, history_search.term))
     }
     
 }
 
+/// REPL 内置命令
+#[derive(Debug, PartialEq)]
+pub enum MetaCommand {
+    /// 查看当前超时
+    Timeout,
+    /// 设置默认超时
+    SetTimeout(u64),
+    /// 仅本次命令使用的超时
+    Exec { timeout: u64, command: String },
+    /// 切换按完成顺序输出
+    FirstCome,
+    /// 查看或设置输出格式
+    Format(Option<OutputFormat>),
+    /// 按标签表达式重新选择节点，None 恢复启动时的节点组
+    Select(Option<String>),
+    /// 将当前节点保存为节点组，force 覆盖已存在的节点组
+    SaveGroup { name: String, force: bool },
+    /// 重新读取配置并应用变化
+    Reload,
+}
+
+/**
+ * 解析以 `:` 开头的内置命令
+ *      :timeout                    查看当前命令超时
+ *      :timeout <secs>             设置默认命令超时
+ *      :timeout <secs> <command>   以指定超时执行一次命令
+ *      :first-come                 切换按完成顺序/按节点顺序输出
+ *      :format [banner|prefix]     查看或设置输出格式
+ *      :select [expr]              按标签表达式重新选择节点，省略时恢复启动时的节点组
+ *      :save-group <name> [--force]  将当前节点保存为节点组
+ *      :reload                     重新读取配置，连接新增的节点并断开移除的节点
+ * 非内置命令返回 None
+ */
+pub fn parse_meta_command(line: &str) -> Option<Result<MetaCommand, String>> {
+    let rest = line.strip_prefix(':')?;
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let args = args.trim();
+    let res = match name {
+        "timeout" => {
+            if args.is_empty() {
+                Ok(MetaCommand::Timeout)
+            } else {
+                let (secs, command) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                match secs.parse::<u64>() {
+                    Ok(0) | Err(_) => Err(tr!(InvalidTimeout, secs)),
+                    Ok(secs) if command.trim().is_empty() => Ok(MetaCommand::SetTimeout(secs)),
+                    Ok(secs) => Ok(MetaCommand::Exec { timeout: secs, command: command.trim().to_string() }),
+                }
+            }
+        }
+        "first-come" => Ok(MetaCommand::FirstCome),
+        "format" if args.is_empty() => Ok(MetaCommand::Format(None)),
+        "format" => OutputFormat::from_str(args, true)
+            .map(|format| MetaCommand::Format(Some(format)))
+            .map_err(|_| tr!(InvalidFormat, args)),
+        "select" if args.is_empty() => Ok(MetaCommand::Select(None)),
+        "select" => Ok(MetaCommand::Select(Some(args.to_string()))),
+        "save-group" => {
+            let mut words: Vec<&str> = args.split_whitespace().collect();
+            let force = words.contains(&"--force");
+            words.retain(|word| *word != "--force");
+            match words[..] {
+                [name] => Ok(MetaCommand::SaveGroup { name: name.to_string(), force }),
+                _ => Err(tr!(SaveGroupUsage)),
+            }
+        }
+        "reload" => Ok(MetaCommand::Reload),
+        _ => Err(tr!(UnknownCommand, name)),
+    };
+    Some(res)
+}
+
 pub fn is_command_blocked(cmd: &str) -> bool {
     BLOCKED_COMMANDS.iter().any(|&blocked| cmd.starts_with(blocked))
 }
@@ -84,4 +164,33 @@ pub fn is_command_blocked(cmd: &str) -> bool {
 fn get_now() -> String {
     let now = Local::now();
     format!("{:>}", now.format("%Y/%m/%d %H:%M:%S"))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meta_command() {
+        assert_eq!(parse_meta_command("ls -l"), None);
+        assert_eq!(parse_meta_command(":timeout"), Some(Ok(MetaCommand::Timeout)));
+        assert_eq!(parse_meta_command(":timeout 30"), Some(Ok(MetaCommand::SetTimeout(30))));
+        assert_eq!(
+            parse_meta_command(":timeout 5  grep -r foo /var/log"),
+            Some(Ok(MetaCommand::Exec { timeout: 5, command: "grep -r foo /var/log".to_string() }))
+        );
+        assert!(matches!(parse_meta_command(":timeout 0"), Some(Err(_))));
+        assert!(matches!(parse_meta_command(":timeout abc"), Some(Err(_))));
+        assert_eq!(parse_meta_command(":first-come"), Some(Ok(MetaCommand::FirstCome)));
+        assert_eq!(parse_meta_command(":format prefix"), Some(Ok(MetaCommand::Format(Some(OutputFormat::Prefix)))));
+        assert!(matches!(parse_meta_command(":format json"), Some(Err(_))));
+        assert_eq!(parse_meta_command(":select"), Some(Ok(MetaCommand::Select(None))));
+        assert_eq!(parse_meta_command(":select  role=web and dc!=bj"), Some(Ok(MetaCommand::Select(Some("role=web and dc!=bj".to_string())))));
+        assert_eq!(parse_meta_command(":save-group adhoc"), Some(Ok(MetaCommand::SaveGroup { name: "adhoc".to_string(), force: false })));
+        assert_eq!(parse_meta_command(":save-group --force adhoc"), Some(Ok(MetaCommand::SaveGroup { name: "adhoc".to_string(), force: true })));
+        assert!(matches!(parse_meta_command(":save-group"), Some(Err(_))));
+        assert!(matches!(parse_meta_command(":save-group a b"), Some(Err(_))));
+        assert_eq!(parse_meta_command(":reload"), Some(Ok(MetaCommand::Reload)));
+        assert!(matches!(parse_meta_command(":unknown"), Some(Err(_))));
+    }
+}
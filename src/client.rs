@@ -0,0 +1,308 @@
+//! 嵌入用的客户端
+//!
+//! 不读取配置文件、不操作终端，通过 [`ClientBuilder`] 设置服务器、节点及回调后连接，
+//! 命令结果按节点完成顺序以 [`Stream`] 返回。
+//!
+//! ```no_run
+//! use beelog::client::Client;
+//! use beelog::config::ServerInfo;
+//! use futures::StreamExt;
+//!
+//! # async fn run() -> beelog::error::Result<()> {
+//! let mut server = ServerInfo::new("jump.example.com", 2222, "ops");
+//! server.password = Some("env:JUMP_PASSWORD".to_string());
+//! let client = Client::builder()
+//!     .server(server)
+//!     .nodes(["app-01", "app-02"])
+//!     .concurrency(8)
+//!     .command_timeout(30)
+//!     .on_output(|node, chunk| print!("[{}] {}", node, chunk))
+//!     .connect()
+//!     .await?;
+//! let mut results = client.exec("uptime");
+//! while let Some(result) = results.next().await {
+//!     println!("{}: {:?}", result.node, result.result.map(|o| o.status));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+use futures::Stream;
+use tokio_util::sync::CancellationToken;
+use crate::auth::Credentials;
+use crate::config::{NodeGroup, ServerInfo, Timeouts};
+use crate::error::{BeelogError, Result};
+use crate::interaction::{Interaction, NonInteractive};
+use crate::jump_server_helper::{Helper, NoProgress, NodeResult, Progress};
+use crate::mfa::MfaProvider;
+use crate::tr;
+
+type OutputHandler = Box<dyn Fn(&str, &str) + Send + Sync>;
+type ConnectHandler = Box<dyn Fn(&str, Option<&BeelogError>) + Send + Sync>;
+
+/// 客户端构建器，通过 [`Client::builder`] 创建
+pub struct ClientBuilder {
+    server: Option<ServerInfo>,
+    node_group: NodeGroup,
+    concurrency: Option<usize>,
+    retries: Option<u32>,
+    timeouts: Option<Timeouts>,
+    command_timeout: Option<u64>,
+    interaction: Arc<dyn Interaction>,
+    mfa: Option<Arc<dyn MfaProvider>>,
+    progress: Arc<dyn Progress>,
+    on_output: Option<OutputHandler>,
+    on_connect: Option<ConnectHandler>,
+}
+
+impl ClientBuilder {
+
+    /// JumpServer 服务器信息，认证方式、密码等在 [`ServerInfo`] 中设置
+    pub fn server(mut self, server: ServerInfo) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// 追加要连接的节点
+    pub fn nodes<I, S>(mut self, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.node_group.nodes.extend(nodes.into_iter().map(Into::into));
+        self
+    }
+
+    /// 追加一个节点，并指定校验用的期望主机名或IP
+    pub fn node_expecting(mut self, node: impl Into<String>, expected_host: impl Into<String>) -> Self {
+        let node = node.into();
+        self.node_group.expected_hosts.insert(node.clone(), expected_host.into());
+        self.node_group.nodes.push(node);
+        self
+    }
+
+    /// 同时连接的节点数上限
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// 可恢复错误的重试次数
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// 各连接阶段及命令执行的超时时间
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = Some(timeouts);
+        self
+    }
+
+    /// 命令执行的默认超时，单位秒
+    pub fn command_timeout(mut self, secs: u64) -> Self {
+        self.command_timeout = Some(secs);
+        self
+    }
+
+    /// 公钥确认、私钥密码等交互，默认 [`NonInteractive`]
+    pub fn interaction(mut self, interaction: Arc<dyn Interaction>) -> Self {
+        self.interaction = interaction;
+        self
+    }
+
+    /// MFA 动态码来源，优先于 [`ServerInfo::secret_code`]
+    pub fn mfa_provider(mut self, provider: Arc<dyn MfaProvider>) -> Self {
+        self.mfa = Some(provider);
+        self
+    }
+
+    /// 完整的进度回调，与 `on_output`、`on_connect` 可同时使用
+    pub fn progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// 节点收到输出时回调，参数为节点名称及输出片段
+    pub fn on_output(mut self, handler: impl Fn(&str, &str) + Send + Sync + 'static) -> Self {
+        self.on_output = Some(Box::new(handler));
+        self
+    }
+
+    /// 单个节点连接结束时回调，失败时带有错误
+    pub fn on_connect(mut self, handler: impl Fn(&str, Option<&BeelogError>) + Send + Sync + 'static) -> Self {
+        self.on_connect = Some(Box::new(handler));
+        self
+    }
+
+    /**
+     * 连接所有节点
+     * 部分节点失败时仍返回客户端，失败的节点通过 [`Client::failures`] 获取
+     * 未设置服务器或节点、凭据准备失败时返回错误
+     */
+    pub async fn connect(self) -> Result<Client> {
+        let mut server = self.server.ok_or_else(|| BeelogError::Config(tr!(ClientNoServer)))?;
+        if self.node_group.nodes.is_empty() {
+            return Err(BeelogError::Config(tr!(ClientNoNodes)));
+        }
+        if let Some(timeouts) = self.timeouts {
+            server.timeouts = timeouts;
+        }
+        if let Some(secs) = self.command_timeout {
+            server.timeouts.command = secs;
+        }
+        if self.concurrency.is_some() {
+            server.max_concurrency = self.concurrency;
+        }
+        if let Some(retries) = self.retries {
+            server.retries = retries;
+        }
+
+        let mut credentials = Credentials::prepare(&server, self.interaction.as_ref())?;
+        if let Some(provider) = self.mfa {
+            credentials = credentials.with_mfa(provider);
+        }
+        let progress = Callbacks {
+            inner: self.progress,
+            on_output: self.on_output,
+            on_connect: self.on_connect,
+        };
+        let command_timeout = server.timeouts.command;
+        let (helper, failures) = Helper::connect_with(server, self.node_group, credentials, self.interaction, CancellationToken::new(), &progress).await;
+        Ok(Client {
+            helper,
+            failures,
+            command_timeout,
+            progress,
+        })
+    }
+}
+
+/// 已连接的客户端
+pub struct Client {
+    helper: Helper,
+    failures: Vec<(String, BeelogError)>,
+    command_timeout: u64,
+    progress: Callbacks,
+}
+
+impl Client {
+
+    /// 创建构建器
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder {
+            server: None,
+            node_group: NodeGroup::default(),
+            concurrency: None,
+            retries: None,
+            timeouts: None,
+            command_timeout: None,
+            interaction: Arc::new(NonInteractive),
+            mfa: None,
+            progress: Arc::new(NoProgress),
+            on_output: None,
+            on_connect: None,
+        }
+    }
+
+    /// 已连接的节点
+    pub fn nodes(&self) -> Vec<String> {
+        self.helper.nodes()
+    }
+
+    /// 连接失败的节点，按节点顺序排列
+    pub fn failures(&self) -> &[(String, BeelogError)] {
+        &self.failures
+    }
+
+    /// 命令执行的默认超时，单位秒
+    pub fn command_timeout(&self) -> u64 {
+        self.command_timeout
+    }
+
+    /// 修改命令执行的默认超时
+    pub fn set_command_timeout(&mut self, secs: u64) {
+        self.command_timeout = secs;
+    }
+
+    /// 在所有节点执行命令，按完成顺序返回各节点结果
+    pub fn exec<'a>(&'a self, command: &'a str) -> impl Stream<Item = NodeResult> + 'a {
+        self.exec_with(command, self.command_timeout, CancellationToken::new())
+    }
+
+    /// 指定超时及取消令牌执行命令，cancel 触发时中断所有仍在执行的节点
+    pub fn exec_with<'a>(&'a self, command: &'a str, timeout_secs: u64, cancel: CancellationToken) -> impl Stream<Item = NodeResult> + 'a {
+        self.helper.exec_stream(command, timeout_secs, cancel, &self.progress)
+    }
+
+    /// 在所有节点执行命令，等待全部结束后按节点顺序返回结果
+    pub async fn exec_all(&self, command: &str) -> Vec<NodeResult> {
+        self.helper.exec(command, self.command_timeout, CancellationToken::new(), &self.progress).await
+    }
+
+    /// 关闭所有连接，返回关闭失败的节点
+    pub async fn close(mut self) -> Vec<(String, BeelogError)> {
+        self.helper.close(&self.progress).await
+    }
+}
+
+/// 在调用方的进度回调之外，调用构建器中设置的输出及连接回调
+struct Callbacks {
+    inner: Arc<dyn Progress>,
+    on_output: Option<OutputHandler>,
+    on_connect: Option<ConnectHandler>,
+}
+
+impl Progress for Callbacks {
+
+    fn connect_started(&self, nodes: &[String]) {
+        self.inner.connect_started(nodes);
+    }
+
+    fn node_connected(&self, node: &str, error: Option<&BeelogError>) {
+        if let Some(handler) = &self.on_connect {
+            handler(node, error);
+        }
+        self.inner.node_connected(node, error);
+    }
+
+    fn connect_finished(&self) {
+        self.inner.connect_finished();
+    }
+
+    fn exec_started(&self, nodes: &[String]) {
+        self.inner.exec_started(nodes);
+    }
+
+    fn node_output(&self, node: &str, chunk: &str) {
+        if let Some(handler) = &self.on_output {
+            handler(node, chunk);
+        }
+        self.inner.node_output(node, chunk);
+    }
+
+    fn node_interrupting(&self, node: &str) {
+        self.inner.node_interrupting(node);
+    }
+
+    fn node_finished(&self, result: &NodeResult) {
+        self.inner.node_finished(result);
+    }
+
+    fn exec_finished(&self) {
+        self.inner.exec_finished();
+    }
+
+    fn close_started(&self, nodes: &[String]) {
+        self.inner.close_started(nodes);
+    }
+
+    fn node_closed(&self, node: &str, error: Option<&BeelogError>) {
+        self.inner.node_closed(node, error);
+    }
+
+    fn close_finished(&self) {
+        self.inner.close_finished();
+    }
+}
@@ -1,11 +1,32 @@
+use clap::ValueEnum;
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::Table;
 use super::args::Args;
+use crate::config_check::Diagnostic;
+use crate::error::{BeelogError, Result};
+use crate::i18n::{self, Locale};
+use crate::interaction::{Interaction, NonInteractive};
+use crate::inventory::InventorySource;
+use crate::node_pattern;
+use crate::nodes_cmd;
+use crate::selector::Selector;
+use crate::tr;
 
 const CONFIG_FILE_NAME: &str = "config.toml";
 const HISTORY_FILE_NAME: &str = "history.txt";
+const KNOWN_HOSTS_FILE_NAME: &str = "known_hosts";
+const CONF_D_DIR_NAME: &str = "conf.d";
+const CACHE_DIR_NAME: &str = "cache";
+/// 指定配置文件路径的环境变量
+const CONFIG_ENV: &str = "BEELOG_CONFIG";
+/// 引用共享配置的键
+const EXTENDS_KEY: &str = "extends";
+/// 引用外部清单的键
+const INVENTORY_KEY: &str = "inventory";
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
@@ -14,16 +35,31 @@ pub struct Config {
 
 #[derive(Debug, Deserialize)]
 pub struct ServerConfig {
-    #[serde(rename = "default-server")]
-    pub default_server: String,
+    /// 默认服务器，未配置且未通过 -s 指定时提示选择
+    #[serde(rename = "default-server", default)]
+    pub default_server: Option<String>,
 
-    #[serde(rename = "default-node-group")]
-    pub default_node_group: String,
+    /// 默认节点组，未配置、未通过 -n 指定或不适用于所选服务器时提示选择
+    #[serde(rename = "default-node-group", default)]
+    pub default_node_group: Option<String>,
 
+    #[serde(default)]
     pub servers: Vec<ServerInfo>,
 
-    #[serde(rename = "node-groups")]
+    #[serde(rename = "node-groups", default)]
     pub node_groups: Vec<NodeGroup>,
+
+    /// 界面语言 zh-CN / en，未配置时按 LANG 等环境变量选择
+    #[serde(default)]
+    pub language: Option<Locale>,
+
+    /// 节点标签，键为节点名称，支持范围；多个键匹配同一节点时合并其标签，精确的节点名称优先
+    #[serde(rename = "node-labels", default)]
+    pub node_labels: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// 外部清单，可多个：Ansible 清单（`ansible:` 前缀或其他文件）、ssh 配置（`ssh:` 前缀或文件名为 config / ssh_config）
+    #[serde(default, deserialize_with = "string_or_list")]
+    pub inventory: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -33,17 +69,248 @@ pub struct ServerInfo {
     pub host: String,
     pub port: u16,
     pub user: String,
-    pub key_path: String,
+    /// 私钥路径，支持 `~` 及环境变量
+    #[serde(default)]
+    pub key_path: Option<String>,
+    /// 私钥密码来源，加密私钥未配置时会在连接前提示输入
+    #[serde(default)]
+    pub key_passphrase: Option<String>,
+    /// 登录密码来源，支持 `env:NAME`、`file:path`、`cmd:command` 或明文
+    #[serde(default)]
+    pub password: Option<String>,
     #[serde(default)]
     pub secret_code: Option<String>,
+    /// 按顺序尝试的认证方式
+    #[serde(default = "default_auth_methods")]
+    pub auth_methods: Vec<AuthMethod>,
+    /// 服务器公钥校验策略
+    #[serde(default)]
+    pub host_key_policy: HostKeyPolicy,
+    /// 连接后是否校验所有节点的主机名/IP，默认关闭；配置了 expected_hosts 的节点始终校验
+    #[serde(default)]
+    pub verify_host: bool,
+    /// 同时连接的节点数上限，可通过 --parallel 覆盖
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// 连接失败（网络、超时等可恢复错误）时的重试次数
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// 各连接阶段的超时时间
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    /// 命令结果的输出格式，可通过 --format 覆盖
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// 进入节点后切换的工作目录，节点组中配置的优先
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// 进入节点后设置的环境变量，与节点组中的合并，同名时以节点组为准
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// 进入节点后依次执行的命令，先于节点组中的命令执行
+    #[serde(default)]
+    pub init_commands: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl ServerInfo {
+
+    /// 不经配置文件创建服务器信息，名称使用 host，其余字段与配置缺省值一致
+    pub fn new(host: impl Into<String>, port: u16, user: impl Into<String>) -> Self {
+        let host = host.into();
+        ServerInfo {
+            name: host.clone(),
+            host,
+            port,
+            user: user.into(),
+            key_path: None,
+            key_passphrase: None,
+            password: None,
+            secret_code: None,
+            auth_methods: default_auth_methods(),
+            host_key_policy: HostKeyPolicy::default(),
+            verify_host: false,
+            max_concurrency: None,
+            retries: default_retries(),
+            timeouts: Timeouts::default(),
+            output_format: OutputFormat::default(),
+            working_dir: None,
+            env: BTreeMap::new(),
+            init_commands: Vec::new(),
+        }
+    }
+}
+
+/// 连接各阶段的超时时间，单位秒
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Timeouts {
+    /// TCP 连接
+    pub tcp: u64,
+    /// ssh 握手
+    pub handshake: u64,
+    /// 认证
+    pub auth: u64,
+    /// JumpServer 菜单就绪及进入节点
+    pub navigation: u64,
+    /// 命令执行的默认超时，REPL 中可通过 `:timeout` 调整
+    pub command: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            tcp: 20,
+            handshake: 10,
+            auth: 30,
+            navigation: 30,
+            command: 60 * 20,
+        }
+    }
+}
+
+/// 认证方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMethod {
+    /// ssh-agent
+    Agent,
+    /// 私钥文件
+    Publickey,
+    /// 密码
+    Password,
+    /// 键盘交互，用于MFA
+    KeyboardInteractive,
+}
+
+/// 命令结果的输出格式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// 每个节点一个 `======node=======` 块
+    #[default]
+    Banner,
+    /// 每行添加节点前缀 `node: line`，便于 grep/sort
+    Prefix,
+}
+
+/// 服务器公钥校验策略
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostKeyPolicy {
+    /// 仅允许 known_hosts 中已记录的公钥
+    Strict,
+    /// 首次连接确认指纹后记录，之后严格校验；无法确认时拒绝连接
+    #[default]
+    AcceptNew,
+    /// 首次连接不经确认直接记录，之后严格校验，用于脚本等无人值守的场景
+    AcceptNewUnattended,
+    /// 不校验
+    Off,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct NodeGroup {
     pub group: String,
+    /// 节点列表，支持 `app-[01-20]`、`10.0.1.[10-30]` 形式的范围
+    #[serde(default)]
     pub nodes: Vec<String>,
+    /// 包含的其他节点组
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// 排除的节点，支持范围
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// 节点期望的主机名或IP，配置后始终校验；未配置时在 verify_host 开启时使用节点名称校验
+    #[serde(default)]
+    pub expected_hosts: HashMap<String, String>,
+    /// 适用的服务器名称，为空时适用于所有服务器
+    #[serde(default)]
+    pub servers: Vec<String>,
+    /// 输出节点列表的本地命令，其节点与 nodes 合并；选择服务器后执行
+    #[serde(default)]
+    pub nodes_cmd: Option<String>,
+    /// nodes_cmd 的超时时间（秒），默认 10
+    #[serde(default)]
+    pub nodes_cmd_timeout: Option<u64>,
+    /// nodes_cmd 输出的缓存有效期（秒），默认 300，0 表示不缓存
+    #[serde(default)]
+    pub nodes_cmd_ttl: Option<u64>,
+    /// nodes_cmd 输出的节点，原样使用，不展开范围
+    #[serde(skip)]
+    pub dynamic_nodes: Vec<String>,
+    /// 进入节点后切换的工作目录
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// 进入节点后设置的环境变量
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// 进入节点后依次执行的命令，如 `sudo -iu appuser`
+    #[serde(default)]
+    pub init_commands: Vec<String>,
 }
 
+impl NodeGroup {
+
+    /// 是否适用于指定服务器
+    pub fn applies_to(&self, server: &str) -> bool {
+        self.servers.is_empty() || self.servers.iter().any(|s| s == server)
+    }
+
+    /// 合并其他节点组的初始化配置：工作目录及同名环境变量以其为准，命令追加在后，已有的命令不重复执行
+    fn merge_init(&mut self, other: &NodeGroup) {
+        if other.working_dir.is_some() {
+            self.working_dir = other.working_dir.clone();
+        }
+        self.env.extend(other.env.clone());
+        for command in &other.init_commands {
+            if !self.init_commands.contains(command) {
+                self.init_commands.push(command.clone());
+            }
+        }
+    }
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_auth_methods() -> Vec<AuthMethod> {
+    vec![AuthMethod::Publickey, AuthMethod::KeyboardInteractive]
+}
+
+/// 单个字符串或字符串数组
+fn string_or_list<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(value) => vec![value],
+        StringOrList::List(values) => values,
+    })
+}
+
+
+/// 读取配置得到的服务器及节点
+#[derive(Debug, Clone)]
+pub struct SelectedServer {
+    pub server_info: ServerInfo,
+    pub node_group: NodeGroup,
+    /// 该服务器可用的节点，用于 REPL 中重新选择
+    pub inventory: NodeInventory,
+    /// 节点的选择方式，重新读取配置时按此重新选择
+    pub selection: NodeSelection,
+}
+
+/// 节点的选择方式：节点组、标签表达式或两者同时使用
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeSelection {
+    pub group: Option<String>,
+    /// 标签表达式，未指定节点组时从服务器的所有节点中选择
+    pub select: Option<String>,
+}
 
 /**
  * 读取服务器信息
@@ -51,79 +318,1047 @@ pub struct NodeGroup {
  * args 传参
  *      server 服务器名称，如果为空则使用配置文件中的默认服务器
  *      node_group 节点组名称，如果为空则使用配置文件中的默认节点
+ *      select 标签表达式，指定时从节点组（未指定节点组时为服务器的所有节点）中选择匹配的节点
+ * interaction 未指定且没有默认值时，通过其在候选项中选择；只有一个候选项时直接使用
  *
- * return 返回服务器信息和节点组列表
+ * return 返回服务器信息、节点组及服务器可用的节点
  *
- * Error 如果未找到服务器或节点组配置，则返回错误
+ * Error 名称重复、未找到或未选择服务器或节点组、表达式无效或没有匹配的节点，则返回错误
  */
-pub fn read_server_config(args: &Args) -> Result<(ServerInfo, NodeGroup), Box<dyn std::error::Error>> {
-    let config = load_config()?;
-    let server_config = config.server;
-    let arg_server = args.server.as_ref().unwrap_or(&server_config.default_server);
+pub fn read_server_config(args: &Args, interaction: &dyn Interaction) -> Result<SelectedServer> {
+    let mut server_config = read_config(args.config.as_deref())?;
+    let mut server_info = choose_server(&mut server_config, args.server.as_deref(), interaction)?;
+    let mut node_groups = server_config.node_groups;
+    let nodes_cmd_errors = load_dynamic_nodes(&mut node_groups, &server_info.name)?;
+    let mut inventory = NodeInventory::new(&server_info.name, node_groups, &server_config.node_labels)?;
+    inventory.nodes_cmd_errors = nodes_cmd_errors;
+
+    let selection = match &args.select {
+        // 按标签选择且未指定节点组时，从服务器的所有节点中选择
+        Some(expr) if args.node_group.is_none() => NodeSelection { group: None, select: Some(expr.clone()) },
+        select => {
+            let group = choose_node_group(args, server_config.default_node_group.as_deref(), &inventory, interaction)?;
+            NodeSelection { group: Some(group), select: select.clone() }
+        }
+    };
+    let node_group = inventory.resolve(&selection)?;
+    apply_args(&mut server_info, args);
+
+    Ok(SelectedServer {
+        server_info,
+        node_group,
+        inventory,
+        selection,
+    })
+}
+
+/**
+ * 重新读取配置，按原服务器名称及选择方式重新选择节点，不进行交互选择
+ * 返回的服务器信息同样应用 --parallel / --format 参数
+ *
+ * Error 配置无效、服务器已不存在或按原方式无法选择节点，则返回错误
+ */
+pub fn reload_server_config(args: &Args, server: &str, selection: &NodeSelection) -> Result<SelectedServer> {
+    let mut server_config = read_config(args.config.as_deref())?;
+    let mut server_info = choose_server(&mut server_config, Some(server), &NonInteractive)?;
+    let mut node_groups = server_config.node_groups;
+    let nodes_cmd_errors = load_dynamic_nodes(&mut node_groups, &server_info.name)?;
+    let mut inventory = NodeInventory::new(&server_info.name, node_groups, &server_config.node_labels)?;
+    inventory.nodes_cmd_errors = nodes_cmd_errors;
+    let node_group = inventory.resolve(selection)?;
+    apply_args(&mut server_info, args);
+
+    Ok(SelectedServer {
+        server_info,
+        node_group,
+        inventory,
+        selection: selection.clone(),
+    })
+}
+
+/// 命令行参数覆盖配置
+fn apply_args(server_info: &mut ServerInfo, args: &Args) {
+    if args.parallel.is_some() {
+        server_info.max_concurrency = args.parallel;
+    }
+    if let Some(format) = args.format {
+        server_info.output_format = format;
+    }
+}
+
+/// 重新读取的配置与当前状态的差异
+#[derive(Debug, Default, PartialEq)]
+pub struct ReloadDiff {
+    /// 新增及移除的适用于该服务器的节点组
+    pub added_groups: Vec<String>,
+    pub removed_groups: Vec<String>,
+    /// 需新连接及需断开的节点
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    /// 服务器的连接参数有变化，已连接的节点仍使用原参数，需重新连接才能生效
+    pub server_changed: bool,
+}
+
+impl ReloadDiff {
+
+    /// connected 为当前已连接的节点
+    pub fn new(current: &SelectedServer, connected: &[String], reloaded: &SelectedServer) -> Self {
+        let (current_groups, reloaded_groups) = (current.inventory.group_names(), reloaded.inventory.group_names());
+        let mut reloaded_info = reloaded.server_info.clone();
+        // 输出格式及命令超时不影响连接，可直接应用
+        reloaded_info.output_format = current.server_info.output_format;
+        reloaded_info.timeouts.command = current.server_info.timeouts.command;
+        ReloadDiff {
+            added_groups: reloaded_groups.iter().filter(|group| !current_groups.contains(group)).map(|group| group.to_string()).collect(),
+            removed_groups: current_groups.iter().filter(|group| !reloaded_groups.contains(group)).map(|group| group.to_string()).collect(),
+            added_nodes: reloaded.node_group.nodes.iter().filter(|node| !connected.contains(node)).cloned().collect(),
+            removed_nodes: connected.iter().filter(|node| !reloaded.node_group.nodes.contains(node)).cloned().collect(),
+            server_changed: reloaded_info != current.server_info,
+        }
+    }
+
+    /// 节点组、节点及服务器均没有变化
+    pub fn is_empty(&self) -> bool {
+        *self == ReloadDiff::default()
+    }
+}
+
+/**
+ * 读取服务器信息，不选择节点组
+ * server 为空时使用默认服务器，没有默认服务器时通过 interaction 选择
+ */
+pub fn read_server_info(config: Option<&Path>, server: Option<&str>, interaction: &dyn Interaction) -> Result<ServerInfo> {
+    let mut server_config = read_config(config)?;
+    choose_server(&mut server_config, server, interaction)
+}
+
+/// 读取配置并按其设置语言，名称重复时返回错误
+fn read_config(config: Option<&Path>) -> Result<ServerConfig> {
+    let server_config = load_config(&get_config_file_path(config)?)?.server;
+    if let Some(locale) = server_config.language {
+        i18n::set_locale(locale);
+    }
+    if let Some(name) = find_duplicate(server_config.servers.iter().map(|server| server.name.as_str())) {
+        return Err(BeelogError::Config(tr!(DuplicateServer, name)));
+    }
+    if let Some(name) = find_duplicate(server_config.node_groups.iter().map(|group| group.group.as_str())) {
+        return Err(BeelogError::Config(tr!(DuplicateNodeGroup, name)));
+    }
+    Ok(server_config)
+}
+
+/// 确定服务器：指定的名称 > 默认服务器 > 在所有服务器中选择；从 servers 中取出所选服务器
+fn choose_server(server_config: &mut ServerConfig, server: Option<&str>, interaction: &dyn Interaction) -> Result<ServerInfo> {
+    let server_name = match server.or(server_config.default_server.as_deref()) {
+        Some(name) => name.to_string(),
+        None => {
+            let names = server_config.servers.iter().map(|server| server.name.clone()).collect();
+            choose(interaction, &tr!(ChooseServer), names)
+                .ok_or_else(|| BeelogError::Config(tr!(ServerNotSelected)))?
+        }
+    };
+    let index = server_config.servers.iter()
+        .position(|server| server.name == server_name)
+        .ok_or_else(|| BeelogError::Config(tr!(ServerNotFound, server_name)))?;
+    Ok(server_config.servers.remove(index))
+}
+
+/**
+ * 执行适用于该服务器的节点组及其 include 的节点组的 nodes_cmd，输出的节点记录在 dynamic_nodes
+ * 多个命令同时执行；失败的节点组只保留配置的节点，返回各节点组的错误，选择该节点组时才报错
+ */
+fn load_dynamic_nodes(groups: &mut [NodeGroup], server: &str) -> Result<Vec<(String, BeelogError)>> {
+    let applicable: Vec<&str> = groups.iter()
+        .filter(|group| group.applies_to(server))
+        .map(|group| group.group.as_str())
+        .collect();
+    let needed = with_includes(groups, applicable);
+    let commands: Vec<(usize, &str)> = groups.iter().enumerate()
+        .filter(|(_, group)| needed.contains(&group.group.as_str()))
+        .filter_map(|(index, group)| group.nodes_cmd.as_deref().map(|command| (index, command)))
+        .collect();
+    if commands.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let cache_dir = get_cache_dir()?;
+    let results: Vec<(usize, Result<nodes_cmd::DynamicNodes>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = commands.iter()
+            .map(|&(index, command)| {
+                let group = &groups[index];
+                let timeout = Duration::from_secs(group.nodes_cmd_timeout.unwrap_or(nodes_cmd::DEFAULT_TIMEOUT));
+                let ttl = Duration::from_secs(group.nodes_cmd_ttl.unwrap_or(nodes_cmd::DEFAULT_TTL));
+                let cache_dir = &cache_dir;
+                (index, scope.spawn(move || nodes_cmd::load(&group.group, command, timeout, ttl, cache_dir)))
+            })
+            .collect();
+        handles.into_iter()
+            .map(|(index, handle)| (index, handle.join().expect("nodes_cmd thread")))
+            .collect()
+    });
+    let mut errors = Vec::new();
+    for (index, result) in results {
+        let group = &mut groups[index];
+        match result {
+            Ok(dynamic) => {
+                group.dynamic_nodes.extend(dynamic.nodes);
+                group.expected_hosts.extend(dynamic.expected_hosts);
+            }
+            Err(e) => {
+                tracing::warn!(group = group.group, error = %e, "节点命令失败");
+                errors.push((group.group.clone(), e));
+            }
+        }
+    }
+    Ok(errors)
+}
+
+/// 节点组及其直接或间接 include 的节点组名称
+fn with_includes<'a>(groups: &'a [NodeGroup], mut names: Vec<&'a str>) -> Vec<&'a str> {
+    let mut index = 0;
+    while let Some(&name) = names.get(index) {
+        let includes = groups.iter().filter(|group| group.group == name).flat_map(|group| &group.include);
+        for include in includes {
+            if !names.contains(&include.as_str()) {
+                names.push(include);
+            }
+        }
+        index += 1;
+    }
+    names
+}
+
+/// 确定节点组：-n 参数 > 适用于该服务器的默认节点组 > 在适用的节点组中选择
+fn choose_node_group(args: &Args, default_node_group: Option<&str>, inventory: &NodeInventory, interaction: &dyn Interaction) -> Result<String> {
+    let server = &inventory.server;
+    let applies = |name: &str| inventory.groups.iter().any(|group| group.group == name && group.applies_to(server));
+    match (&args.node_group, default_node_group) {
+        (Some(name), _) if !applies(name) && inventory.groups.iter().any(|group| &group.group == name) => {
+            Err(BeelogError::Config(tr!(NodeGroupNotForServer, name, server)))
+        }
+        (Some(name), _) => Ok(name.clone()),
+        (None, Some(name)) if applies(name) => Ok(name.to_string()),
+        (None, _) => {
+            let names = inventory.groups.iter()
+                .filter(|group| group.applies_to(server))
+                .map(|group| group.group.clone())
+                .collect();
+            choose(interaction, &tr!(ChooseNodeGroup, server), names)
+                .ok_or_else(|| BeelogError::Config(tr!(NodeGroupNotSelected, server)))
+        }
+    }
+}
+
+/// 服务器可用的节点组及节点标签，用于按标签表达式选择节点
+#[derive(Debug, Clone, Default)]
+pub struct NodeInventory {
+    server: String,
+    /// 所有节点组，include 可引用不适用于该服务器的节点组
+    groups: Vec<NodeGroup>,
+    /// 展开范围后各节点的标签
+    labels: BTreeMap<String, BTreeMap<String, String>>,
+    /// nodes_cmd 失败的节点组及错误
+    nodes_cmd_errors: Vec<(String, BeelogError)>,
+}
+
+impl NodeInventory {
+
+    /// node_labels 的键支持范围，展开后合并同一节点的标签，同名标签以精确的节点名称为准
+    pub fn new(server: &str, groups: Vec<NodeGroup>, node_labels: &BTreeMap<String, BTreeMap<String, String>>) -> Result<Self> {
+        let mut labels: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let (ranges, exact): (Vec<_>, Vec<_>) = node_labels.iter().partition(|(pattern, _)| pattern.contains('['));
+        for (pattern, node_labels) in ranges.into_iter().chain(exact) {
+            for node in node_pattern::expand(pattern)? {
+                labels.entry(node).or_default().extend(node_labels.clone());
+            }
+        }
+        Ok(NodeInventory {
+            server: server.to_string(),
+            groups,
+            labels,
+            nodes_cmd_errors: Vec::new(),
+        })
+    }
+
+    /// 适用于该服务器的节点组名称
+    pub fn group_names(&self) -> Vec<&str> {
+        self.groups.iter()
+            .filter(|group| group.applies_to(&self.server))
+            .map(|group| group.group.as_str())
+            .collect()
+    }
+
+    /// nodes_cmd 失败的节点组及错误，这些节点组只包含配置的节点
+    pub fn nodes_cmd_errors(&self) -> &[(String, BeelogError)] {
+        &self.nodes_cmd_errors
+    }
+
+    /**
+     * 按选择方式得到节点，未指定节点组及表达式时为服务器的所有节点
+     *
+     * Error 指定的节点组或其 include 的节点组 nodes_cmd 失败时返回该错误；从所有节点中选择时不报错
+     */
+    pub fn resolve(&self, selection: &NodeSelection) -> Result<NodeGroup> {
+        if let Some(group) = &selection.group {
+            let needed = with_includes(&self.groups, vec![group.as_str()]);
+            if let Some((_, e)) = self.nodes_cmd_errors.iter().find(|(name, _)| needed.contains(&name.as_str())) {
+                return Err(e.clone());
+            }
+        }
+        match (&selection.group, &selection.select) {
+            (group, Some(expr)) => self.select(expr, group.as_deref()),
+            (Some(group), None) => resolve_node_group(&self.groups, group),
+            (None, None) => self.all_nodes(),
+        }
+    }
+
+    /// 节点的标签
+    pub fn labels(&self, node: &str) -> Option<&BTreeMap<String, String>> {
+        self.labels.get(node)
+    }
 
-    let mut server_info_opt = None;
-    for info in server_config.servers {
-        if arg_server.eq(&info.name) {
-            server_info_opt = Some(info);
-            break;
+    /**
+     * 服务器可用的所有节点：适用于该服务器的节点组中的节点，合并各节点组的初始化配置
+     * 只配置了标签、不在适用的节点组中的节点不包括在内，避免通过其他服务器访问
+     */
+    pub fn all_nodes(&self) -> Result<NodeGroup> {
+        let mut all = NodeGroup {
+            group: self.server.clone(),
+            ..NodeGroup::default()
+        };
+        for group in self.groups.iter().filter(|group| group.applies_to(&self.server)) {
+            let resolved = resolve_node_group(&self.groups, &group.group)?;
+            all.merge_init(&resolved);
+            all.nodes.extend(resolved.nodes);
+            all.expected_hosts.extend(resolved.expected_hosts);
         }
+        all.nodes.sort_by(|a, b| node_pattern::natural_cmp(a, b));
+        all.nodes.dedup();
+        Ok(all)
     }
-    if None == server_info_opt {
-        return Err(Error::new(ErrorKind::NotFound, format!("未找到server配置: {}", arg_server)).into())
+
+    /**
+     * 按标签表达式选择节点
+     * 指定 group 时从该节点组中选择，否则从服务器的所有节点中选择
+     * 返回的节点组以表达式命名
+     *
+     * Error 表达式无效、节点组不存在或没有匹配的节点，则返回错误
+     */
+    pub fn select(&self, expr: &str, group: Option<&str>) -> Result<NodeGroup> {
+        let selector = Selector::parse(expr)?;
+        let mut selected = match group {
+            Some(group) => resolve_node_group(&self.groups, group)?,
+            None => self.all_nodes()?,
+        };
+        let empty = BTreeMap::new();
+        selected.nodes.retain(|node| selector.matches(self.labels.get(node).unwrap_or(&empty)));
+        if selected.nodes.is_empty() {
+            return Err(BeelogError::Config(tr!(SelectNoNodes, expr)));
+        }
+        selected.expected_hosts.retain(|node, _| selected.nodes.contains(node));
+        selected.group = match group {
+            Some(group) => format!("{}[{}]", group, expr.trim()),
+            None => expr.trim().to_string(),
+        };
+        Ok(selected)
     }
-    let arg_node_group = args.node_group.as_ref().unwrap_or(&server_config.default_node_group);
-    let mut node_group_opt = None;
-    for group in server_config.node_groups {
-        if arg_node_group.eq(&group.group) {
-            node_group_opt = Some(group);
+}
+
+/**
+ * 展开节点组
+ * 依次合并 include 的节点组及本组的节点，展开范围，去除 exclude 中的节点，去重后按自然顺序排序
+ * 初始化配置同样先合并 include 的节点组，本组的优先
+ * 返回的节点组 include / exclude 为空，不执行 nodes_cmd
+ *
+ * Error 节点组不存在、include 循环引用或范围无效，则返回错误
+ */
+pub fn resolve_node_group(groups: &[NodeGroup], name: &str) -> Result<NodeGroup> {
+    resolve_group(groups, name, &mut Vec::new())
+}
+
+/// chain 记录 include 链，用于发现循环引用
+fn resolve_group<'a>(groups: &'a [NodeGroup], name: &'a str, chain: &mut Vec<&'a str>) -> Result<NodeGroup> {
+    let group = groups.iter()
+        .find(|group| group.group == name)
+        .ok_or_else(|| BeelogError::Config(tr!(NodeGroupNotFound, name)))?;
+    if chain.contains(&name) {
+        chain.push(name);
+        return Err(BeelogError::Config(tr!(GroupIncludeCycle, chain.join(" -> "))));
+    }
+
+    let mut nodes = Vec::new();
+    let mut expected_hosts = HashMap::new();
+    let mut init = NodeGroup::default();
+    chain.push(name);
+    for include in &group.include {
+        if !groups.iter().any(|group| &group.group == include) {
+            return Err(BeelogError::Config(tr!(GroupIncludeUnknown, name, include)));
         }
+        let included = resolve_group(groups, include, chain)?;
+        init.merge_init(&included);
+        nodes.extend(included.nodes);
+        expected_hosts.extend(included.expected_hosts);
+    }
+    init.merge_init(group);
+    chain.pop();
+    for pattern in &group.nodes {
+        nodes.extend(node_pattern::expand(pattern)?);
     }
-    if node_group_opt.is_none() {
-        return Err(Error::new(ErrorKind::NotFound, format!("未找到node group配置: {}", arg_node_group)).into())
+    nodes.extend(group.dynamic_nodes.iter().cloned());
+    expected_hosts.extend(group.expected_hosts.clone());
+    let mut excluded = HashSet::new();
+    for pattern in &group.exclude {
+        excluded.extend(node_pattern::expand(pattern)?);
     }
+    nodes.retain(|node| !excluded.contains(node));
+    nodes.sort_by(|a, b| node_pattern::natural_cmp(a, b));
+    nodes.dedup();
 
-    Ok((server_info_opt.unwrap(), node_group_opt.unwrap()))
+    Ok(NodeGroup {
+        group: group.group.clone(),
+        nodes,
+        expected_hosts,
+        servers: group.servers.clone(),
+        working_dir: init.working_dir,
+        env: init.env,
+        init_commands: init.init_commands,
+        ..NodeGroup::default()
+    })
+}
+
+/// 在候选项中选择，没有候选项时返回 None，只有一个时直接使用
+fn choose(interaction: &dyn Interaction, prompt: &str, mut options: Vec<String>) -> Option<String> {
+    match options.len() {
+        0 => None,
+        1 => options.pop(),
+        _ => interaction.choose(prompt, &options).and_then(|index| options.into_iter().nth(index)),
+    }
+}
+
+/// 第一个重复的名称
+fn find_duplicate<'a>(names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut seen = HashSet::new();
+    names.into_iter().find(|name| !seen.insert(*name))
 }
 
 /**
  * 获取命令历史存储路径
  */
-pub fn get_history_path() -> PathBuf {
-    if cfg!(debug_assertions) {
-        PathBuf::from(HISTORY_FILE_NAME)
-    } else {
-        let config_dir = get_config_dir();
-        config_dir.join(HISTORY_FILE_NAME)
+pub fn get_history_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(HISTORY_FILE_NAME))
+}
+
+/**
+ * 获取缓存目录，用于 nodes_cmd 的输出等
+ */
+pub fn get_cache_dir() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(CACHE_DIR_NAME))
+}
+
+/**
+ * 获取 beelog 维护的 known_hosts 路径
+ */
+pub fn get_known_hosts_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(KNOWN_HOSTS_FILE_NAME))
+}
+
+/**
+ * 获取配置文件路径
+ * 优先级：--config 参数 > BEELOG_CONFIG 环境变量 > 配置目录下的 config.toml
+ */
+pub fn get_config_file_path(arg: Option<&Path>) -> Result<PathBuf> {
+    if let Some(path) = arg {
+        return Ok(path.to_path_buf());
+    }
+    match std::env::var(CONFIG_ENV) {
+        Ok(path) if !path.is_empty() => Ok(expand_path(&path)),
+        _ => Ok(get_config_dir()?.join(CONFIG_FILE_NAME)),
     }
 }
 
+/// 参与合并的单个配置文件，已移除 extends
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub content: String,
+    pub table: Table,
+}
+
 /**
  * 加载配置文件
- * 读取用户主目录下的 .config/<package_name>/config.toml 文件
- * 如果文件不存在则返回错误
- * 如果文件存在则解析为 Config 结构体
- */
-fn load_config() -> Result<Config, Box<dyn std::error::Error>>{
-    let config_file_path = if cfg!(debug_assertions) {
-        PathBuf::from(CONFIG_FILE_NAME)
-    } else {
-        let config_dir = get_config_dir();
-        config_dir.join(CONFIG_FILE_NAME)
+ * 按以下顺序合并，后加载的覆盖先加载的：
+ *      1. extends 指定的共享配置（如团队仓库中的配置），可多个，按顺序合并
+ *      2. 配置文件本身
+ *      3. 配置文件同目录下 conf.d 中的 .toml 文件，按文件名排序
+ * server.inventory 指定的外部清单（Ansible 清单、ssh 配置）在以上配置之前合并
+ * 表按键合并；server.servers 按 name、server.node-groups 按 group 合并同名条目，其余数组（包括节点组中的 servers）整体替换
+ */
+fn load_config(path: &Path) -> Result<Config> {
+    load_sources(path)
+        .and_then(|sources| parse_sources(path, &sources))
+        .map_err(|d| BeelogError::Config(d.to_string()))
+}
+
+/**
+ * 按合并顺序读取参与合并的所有配置文件
+ * 语法错误带有所在文件及行列号
+ */
+pub fn load_sources(path: &Path) -> std::result::Result<Vec<ConfigSource>, Diagnostic> {
+    let mut sources = load_layers(path)?;
+    let inventories = load_inventories(path, &merge_sources(&sources))?;
+    sources.splice(0..0, inventories);
+    Ok(sources)
+}
+
+/// 按合并顺序读取配置文件本身、extends 及 conf.d，不含外部清单
+pub fn load_layers(path: &Path) -> std::result::Result<Vec<ConfigSource>, Diagnostic> {
+    if !path.exists() {
+        return Err(Diagnostic::error(tr!(ConfigNotFound, path.display())));
+    }
+    let mut sources = Vec::new();
+    load_layer(path, &mut sources, &mut Vec::new())?;
+    let conf_d = path.parent().unwrap_or(Path::new(".")).join(CONF_D_DIR_NAME);
+    if conf_d.is_dir() {
+        let entries = fs::read_dir(&conf_d)
+            .map_err(|e| Diagnostic::error(tr!(ReadFileFailed, conf_d.display(), e)))?;
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        files.sort();
+        for file in files {
+            load_layer(&file, &mut sources, &mut Vec::new())?;
+        }
+    }
+    Ok(sources)
+}
+
+/**
+ * 读取 server.inventory 指定的外部清单，作为优先级最低的配置
+ * 配置文件中定义的同名 server、node group 整体替换清单中的对应项，不逐项合并；相对路径基于配置文件所在目录
+ */
+pub fn load_inventories(path: &Path, merged: &Table) -> std::result::Result<Vec<ConfigSource>, Diagnostic> {
+    let invalid = || Diagnostic::error(tr!(InventoryInvalid, path.display()));
+    let inventory = merged.get("server").and_then(|server| server.get(INVENTORY_KEY));
+    let inventory: Vec<&str> = match inventory {
+        None => Vec::new(),
+        Some(toml::Value::String(source)) => vec![source.as_str()],
+        Some(toml::Value::Array(sources)) => sources.iter()
+            .map(|source| source.as_str().ok_or_else(invalid))
+            .collect::<std::result::Result<_, _>>()?,
+        Some(_) => return Err(invalid()),
     };
-    if !config_file_path.exists() {
-        return Err(Error::new(ErrorKind::NotFound, format!("配置文件未找到 {}", config_file_path.display())).into());
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut sources: Vec<ConfigSource> = inventory.into_iter()
+        .map(|source| InventorySource::parse(source, dir).load())
+        .collect::<std::result::Result<_, _>>()?;
+    let server = merged.get("server");
+    for source in &mut sources {
+        let Some(toml::Value::Table(inventory)) = source.table.get_mut("server") else { continue };
+        for (key, name_key) in [("servers", "name"), ("node-groups", "group")] {
+            let defined: Vec<&toml::Value> = server
+                .and_then(|server| server.get(key))
+                .and_then(toml::Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.get(name_key))
+                .collect();
+            if let Some(toml::Value::Array(entries)) = inventory.get_mut(key) {
+                entries.retain(|entry| entry.get(name_key).is_none_or(|name| !defined.contains(&name)));
+            }
+        }
+    }
+    Ok(sources)
+}
+
+/**
+ * 合并配置文件并解析
+ * 只有一个文件时直接从原文解析，字段错误可定位到行列号；多个文件合并后无法定位，归于 path
+ */
+pub fn parse_sources(path: &Path, sources: &[ConfigSource]) -> std::result::Result<Config, Diagnostic> {
+    match sources {
+        [source] => toml::from_str(&source.content)
+            .map_err(|e| Diagnostic::error(e.message().trim_end().to_string()).at(&source.path, &source.content, e.span())),
+        _ => toml::Value::Table(merge_sources(sources)).try_into()
+            .map_err(|e: toml::de::Error| Diagnostic::error(e.message().trim_end().to_string()).at(path, "", None)),
+    }
+}
+
+/// 按顺序合并各配置文件
+pub fn merge_sources(sources: &[ConfigSource]) -> Table {
+    let mut merged = Table::new();
+    for source in sources {
+        merge_table(&mut merged, source.table.clone(), "");
+    }
+    merged
+}
+
+/**
+ * 读取单个配置文件，先读取其 extends 指定的文件
+ * extends 的相对路径基于当前文件所在目录；chain 记录加载链，用于发现循环引用
+ */
+fn load_layer(path: &Path, sources: &mut Vec<ConfigSource>, chain: &mut Vec<PathBuf>) -> std::result::Result<(), Diagnostic> {
+    let canonical = fs::canonicalize(path)
+        .map_err(|e| Diagnostic::error(tr!(ReadFileFailed, path.display(), e)))?;
+    if chain.contains(&canonical) {
+        return Err(Diagnostic::error(tr!(ConfigExtendsCycle, path.display())));
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| Diagnostic::error(tr!(ReadFileFailed, path.display(), e)))?;
+    let mut table: Table = toml::from_str(&content)
+        .map_err(|e| Diagnostic::error(e.message().trim_end().to_string()).at(path, &content, e.span()))?;
+
+    let invalid = || Diagnostic::error(tr!(ConfigExtendsInvalid, path.display()));
+    let extends = match table.remove(EXTENDS_KEY) {
+        None => Vec::new(),
+        Some(toml::Value::String(base)) => vec![base],
+        Some(toml::Value::Array(bases)) => bases.into_iter()
+            .map(|base| match base {
+                toml::Value::String(base) => Ok(base),
+                _ => Err(invalid()),
+            })
+            .collect::<std::result::Result<_, _>>()?,
+        Some(_) => return Err(invalid()),
+    };
+    chain.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for base in extends {
+        load_layer(&dir.join(expand_path(&base)), sources, chain)?;
+    }
+    chain.pop();
+    sources.push(ConfigSource {
+        path: path.to_path_buf(),
+        content,
+        table,
+    });
+    Ok(())
+}
+
+/// 将 overlay 合并到 base，path 为 base 在配置中的位置，如 `server`，顶层为空
+fn merge_table(base: &mut Table, overlay: Table, path: &str) {
+    for (key, value) in overlay {
+        let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge_table(base, overlay, &path),
+            (Some(toml::Value::Array(base)), toml::Value::Array(overlay)) => match merge_key(&path) {
+                Some(name_key) => merge_entries(base, overlay, name_key, &path),
+                None => *base = overlay,
+            },
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// 按名称合并数组中的条目，同名条目逐项覆盖，新条目追加到末尾
+fn merge_entries(base: &mut Vec<toml::Value>, overlay: Vec<toml::Value>, name_key: &str, path: &str) {
+    for entry in overlay {
+        let name = entry.get(name_key).cloned();
+        let existing = name.and_then(|name| base.iter_mut().find(|e| e.get(name_key) == Some(&name)));
+        match (existing, entry) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(entry)) => merge_table(existing, entry, path),
+            (_, entry) => base.push(entry),
+        }
+    }
+}
+
+/// 按名称合并的数组及其名称字段，只匹配完整路径，节点组中的 servers 等数组整体替换
+fn merge_key(path: &str) -> Option<&'static str> {
+    match path {
+        "server.servers" => Some("name"),
+        "server.node-groups" => Some("group"),
+        _ => None,
     }
-    let content = fs::read_to_string(config_file_path)?;
-    let config: Config = toml::from_str(&content)?;
-    Ok(config)
 }
 
 /**
  * 获取配置目录
+ * 设置了 XDG_CONFIG_HOME 时使用 $XDG_CONFIG_HOME/beelog，否则为 ~/.config/beelog
  */
-fn get_config_dir() -> PathBuf {
-    let home_dir = dirs::home_dir().unwrap();
+fn get_config_dir() -> Result<PathBuf> {
     // 读取 Cargo.toml 中的 package.name
     let package_name = env!("CARGO_PKG_NAME");
-    home_dir.join(".config").join(package_name)
-}
\ No newline at end of file
+    if let Ok(xdg_config_home) = std::env::var("XDG_CONFIG_HOME")
+        && Path::new(&xdg_config_home).is_absolute() {
+        return Ok(PathBuf::from(xdg_config_home).join(package_name));
+    }
+    let home_dir = dirs::home_dir().ok_or_else(|| BeelogError::Config(tr!(NoHomeDir)))?;
+    Ok(home_dir.join(".config").join(package_name))
+}
+
+/**
+ * 展开路径中的 `~` 及环境变量（`$NAME` / `${NAME}`）
+ * 未定义的环境变量保持原样
+ */
+pub fn expand_path(path: &str) -> PathBuf {
+    expand_path_with(path, dirs::home_dir(), |name| std::env::var(name).ok())
+}
+
+/// 按指定的主目录及环境变量展开路径，测试时无需修改进程的环境变量
+fn expand_path_with(path: &str, home_dir: Option<PathBuf>, var: impl Fn(&str) -> Option<String>) -> PathBuf {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    if (rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\"))
+        && let Some(home_dir) = home_dir {
+        expanded.push_str(&home_dir.to_string_lossy());
+        rest = &rest[1..];
+    }
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        match var(name) {
+            Some(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(&rest[pos..pos + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    expanded.push_str(rest);
+    PathBuf::from(expanded)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use clap::Parser;
+    use std::sync::Mutex;
+
+    /// 记录候选项并选择最后一个
+    #[derive(Default)]
+    struct PickLast {
+        offered: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl Interaction for PickLast {
+        fn confirm_host_key(&self, _host: &str, _port: u16, _key_type: &str, _fingerprint: &str) -> bool {
+            false
+        }
+
+        fn key_passphrase(&self, _key_path: &Path) -> Option<String> {
+            None
+        }
+
+        fn choose(&self, _prompt: &str, options: &[String]) -> Option<usize> {
+            self.offered.lock().unwrap().push(options.to_vec());
+            options.len().checked_sub(1)
+        }
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let expand = |path| expand_path_with(path, Some(PathBuf::from("/home/ops")), |name| (name == "KEY_DIR").then(|| "/opt/keys".to_string()));
+        assert_eq!(expand("~/.ssh/id_rsa"), PathBuf::from("/home/ops/.ssh/id_rsa"));
+        assert_eq!(expand("$KEY_DIR/id_rsa"), PathBuf::from("/opt/keys/id_rsa"));
+        assert_eq!(expand("${KEY_DIR}/id_rsa"), PathBuf::from("/opt/keys/id_rsa"));
+        assert_eq!(expand("$UNDEFINED/id_rsa"), PathBuf::from("$UNDEFINED/id_rsa"));
+        assert_eq!(expand("/etc/id_rsa"), PathBuf::from("/etc/id_rsa"));
+    }
+
+    #[test]
+    fn test_load_config_layers() {
+        let dir = TempDir::new("config");
+        fs::create_dir_all(dir.join("team")).unwrap();
+        fs::create_dir_all(dir.join(CONF_D_DIR_NAME)).unwrap();
+        fs::write(dir.join("team/beelog.toml"), r#"
+            [server]
+            default-server = "prod"
+            default-node-group = "app"
+            [[server.servers]]
+            name = "prod"
+            host = "10.0.0.1"
+            port = 2222
+            user = "team"
+            [[server.node-groups]]
+            group = "app"
+            nodes = ["app1", "app2"]
+            include = ["db"]
+            servers = ["test"]
+        "#).unwrap();
+        fs::write(dir.join(CONFIG_FILE_NAME), r#"
+            extends = "team/beelog.toml"
+            [[server.servers]]
+            name = "prod"
+            user = "alice"
+            [[server.node-groups]]
+            group = "db"
+            nodes = ["db1"]
+        "#).unwrap();
+        fs::write(dir.join(CONF_D_DIR_NAME).join("10-local.toml"), r#"
+            [server]
+            default-node-group = "db"
+            [[server.node-groups]]
+            group = "app"
+            nodes = ["app3"]
+            include = []
+            servers = ["prod"]
+        "#).unwrap();
+
+        let config = load_config(&dir.join(CONFIG_FILE_NAME)).unwrap().server;
+        assert_eq!(config.default_server.as_deref(), Some("prod"));
+        assert_eq!(config.default_node_group.as_deref(), Some("db"));
+        assert_eq!(config.servers.len(), 1);
+        assert_eq!(config.servers[0].host, "10.0.0.1");
+        assert_eq!(config.servers[0].user, "alice");
+        let groups: Vec<_> = config.node_groups.iter().map(|g| g.group.as_str()).collect();
+        assert_eq!(groups, ["app", "db"]);
+        // 节点组中的数组整体替换，不按名称合并
+        assert_eq!(config.node_groups[0].nodes, ["app3"]);
+        assert!(config.node_groups[0].include.is_empty());
+        assert_eq!(config.node_groups[0].servers, ["prod"]);
+
+        fs::write(dir.join("team/beelog.toml"), r#"extends = "../config.toml""#).unwrap();
+        assert!(matches!(load_config(&dir.join(CONFIG_FILE_NAME)), Err(BeelogError::Config(_))));
+    }
+
+    #[test]
+    fn test_load_config_inventory() {
+        let dir = TempDir::new("inventory");
+        fs::write(dir.join("hosts.ini"), "[web]\nweb[01:02]\n[db]\ndb1 ansible_host=10.0.0.5\n").unwrap();
+        fs::write(dir.join("ssh_config"), "Host jump\n  HostName 10.0.0.1\n  Port 2222\n  User ops\n").unwrap();
+        fs::write(dir.join(CONFIG_FILE_NAME), r#"
+            [server]
+            inventory = ["hosts.ini", "ssh_config"]
+            [[server.node-groups]]
+            group = "web"
+            nodes = ["web-canary"]
+        "#).unwrap();
+
+        let config = load_config(&dir.join(CONFIG_FILE_NAME)).unwrap().server;
+        assert_eq!(config.inventory, ["hosts.ini", "ssh_config"]);
+        assert_eq!(config.servers.len(), 1);
+        assert_eq!((config.servers[0].name.as_str(), config.servers[0].port), ("jump", 2222));
+        let groups: Vec<_> = config.node_groups.iter().map(|g| (g.group.as_str(), g.nodes.clone())).collect();
+        assert_eq!(groups, [("db", vec!["db1".to_string()]), ("web", vec!["web-canary".to_string()])]);
+        assert_eq!(config.node_groups[0].expected_hosts.get("db1").map(String::as_str), Some("10.0.0.5"));
+
+        fs::write(dir.join("hosts.ini"), "[web]\nweb[a:c]\n").unwrap();
+        let error = load_config(&dir.join(CONFIG_FILE_NAME)).unwrap_err().to_string();
+        assert!(error.contains("hosts.ini:2:1"), "{}", error);
+    }
+
+    #[test]
+    fn test_read_server_config_scoped_groups() {
+        let dir = TempDir::new("select");
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(&path, r#"
+            [[server.servers]]
+            name = "prod"
+            host = "10.0.0.1"
+            port = 2222
+            user = "alice"
+            [[server.servers]]
+            name = "test"
+            host = "10.0.0.2"
+            port = 2222
+            user = "alice"
+            [[server.node-groups]]
+            group = "prod-app"
+            nodes = ["app1"]
+            servers = ["prod"]
+            [[server.node-groups]]
+            group = "prod-db"
+            nodes = ["db1"]
+            servers = ["prod"]
+            [[server.node-groups]]
+            group = "test-app"
+            nodes = ["app1"]
+            servers = ["test"]
+        "#).unwrap();
+        let config = path.to_str().unwrap();
+
+        let picker = PickLast::default();
+        let selected = read_server_config(&Args::parse_from(["beelog", "-c", config]), &picker).unwrap();
+        assert_eq!((selected.server_info.name.as_str(), selected.node_group.group.as_str()), ("test", "test-app"));
+        assert_eq!(*picker.offered.lock().unwrap(), [vec!["prod".to_string(), "test".to_string()]]);
+
+        let picker = PickLast::default();
+        let selected = read_server_config(&Args::parse_from(["beelog", "-c", config, "-s", "prod"]), &picker).unwrap();
+        assert_eq!(selected.node_group.group, "prod-db");
+        assert_eq!(*picker.offered.lock().unwrap(), [vec!["prod-app".to_string(), "prod-db".to_string()]]);
+
+        let args = Args::parse_from(["beelog", "-c", config, "-s", "prod", "-n", "test-app"]);
+        assert!(matches!(read_server_config(&args, &picker), Err(BeelogError::Config(_))));
+        let args = Args::parse_from(["beelog", "-c", config, "-s", "prod"]);
+        assert!(matches!(read_server_config(&args, &crate::interaction::NonInteractive), Err(BeelogError::Config(_))));
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("name = \"test\"", "name = \"prod\"")).unwrap();
+        assert!(matches!(read_server_config(&Args::parse_from(["beelog", "-c", config]), &picker), Err(BeelogError::Config(_))));
+    }
+
+    #[test]
+    fn test_reload_server_config() {
+        let dir = TempDir::new("reload");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let content = r#"
+            [[server.servers]]
+            name = "prod"
+            host = "10.0.0.1"
+            port = 2222
+            user = "alice"
+            [[server.node-groups]]
+            group = "app"
+            nodes = ["app1", "app2"]
+        "#;
+        fs::write(&path, content).unwrap();
+        let args = Args::parse_from(["beelog", "-c", path.to_str().unwrap(), "-n", "app"]);
+        let current = read_server_config(&args, &crate::interaction::NonInteractive).unwrap();
+        let connected = current.node_group.nodes.clone();
+        let unchanged = reload_server_config(&args, "prod", &current.selection).unwrap();
+        assert!(ReloadDiff::new(&current, &connected, &unchanged).is_empty());
+
+        fs::write(&path, content.replace(r#"["app1", "app2"]"#, r#"["app2", "app3"]"#).replace("[[server.node-groups]]", r#"
+            [server.servers.timeouts]
+            command = 60
+            [[server.node-groups]]
+            group = "db"
+            nodes = ["db1"]
+            [[server.node-groups]]"#)).unwrap();
+        let reloaded = reload_server_config(&args, "prod", &current.selection).unwrap();
+        let diff = ReloadDiff::new(&current, &connected, &reloaded);
+        assert_eq!(diff, ReloadDiff {
+            added_groups: vec!["db".to_string()],
+            added_nodes: vec!["app3".to_string()],
+            removed_nodes: vec!["app1".to_string()],
+            ..ReloadDiff::default()
+        });
+        assert_eq!(reloaded.server_info.timeouts.command, 60);
+
+        fs::write(&path, content.replace("alice", "bob")).unwrap();
+        assert!(ReloadDiff::new(&current, &connected, &reload_server_config(&args, "prod", &current.selection).unwrap()).server_changed);
+        fs::write(&path, content.replace("prod", "test")).unwrap();
+        assert!(matches!(reload_server_config(&args, "prod", &current.selection), Err(BeelogError::Config(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_nodes_cmd_errors() {
+        let dir = TempDir::new("nodes-cmd-errors");
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(&path, r#"
+            [[server.servers]]
+            name = "prod"
+            host = "10.0.0.1"
+            port = 2222
+            user = "alice"
+            [[server.node-groups]]
+            group = "app"
+            nodes = ["app1"]
+            [[server.node-groups]]
+            group = "broken"
+            nodes = ["static1"]
+            nodes_cmd = "exit 3"
+            nodes_cmd_ttl = 0
+            [[server.node-groups]]
+            group = "parent"
+            include = ["broken"]
+            [[server.node-groups]]
+            group = "literal"
+            nodes_cmd = "echo 'odd[1]'"
+            nodes_cmd_ttl = 0
+        "#).unwrap();
+        let config = path.to_str().unwrap();
+
+        let selected = read_server_config(&Args::parse_from(["beelog", "-c", config, "-n", "app"]), &NonInteractive).unwrap();
+        assert_eq!(selected.node_group.nodes, ["app1"]);
+        let errors: Vec<_> = selected.inventory.nodes_cmd_errors().iter().map(|(group, _)| group.as_str()).collect();
+        assert_eq!(errors, ["broken"]);
+        let all = selected.inventory.resolve(&NodeSelection::default()).unwrap();
+        assert_eq!(all.nodes, ["app1", "odd[1]", "static1"]);
+        for group in ["broken", "parent"] {
+            let args = Args::parse_from(["beelog", "-c", config, "-n", group]);
+            assert!(matches!(read_server_config(&args, &NonInteractive), Err(BeelogError::Config(_))));
+        }
+    }
+
+    #[test]
+    fn test_resolve_node_group() {
+        let config: Config = toml::from_str(r#"
+            [[server.node-groups]]
+            group = "web"
+            nodes = ["web-[1-3]"]
+            expected_hosts = { web-1 = "10.0.0.1" }
+            working_dir = "/data/web"
+            env = { LANG = "C", APP = "web" }
+            init_commands = ["unset HISTFILE"]
+            [[server.node-groups]]
+            group = "api"
+            nodes = ["api-[09-10]", "web-1"]
+            [[server.node-groups]]
+            group = "all"
+            include = ["web", "api"]
+            nodes = ["db-1"]
+            exclude = ["web-[2-3]"]
+            env = { APP = "all" }
+            init_commands = ["unset HISTFILE", "sudo -iu app"]
+            [[server.node-groups]]
+            group = "a"
+            include = ["b"]
+            [[server.node-groups]]
+            group = "b"
+            include = ["a"]
+        "#).unwrap();
+        let groups = &config.server.node_groups;
+
+        let all = resolve_node_group(groups, "all").unwrap();
+        assert_eq!(all.nodes, ["api-09", "api-10", "db-1", "web-1"]);
+        assert_eq!(all.expected_hosts.get("web-1").map(String::as_str), Some("10.0.0.1"));
+        assert!(all.include.is_empty() && all.exclude.is_empty());
+        assert_eq!(all.working_dir.as_deref(), Some("/data/web"));
+        assert_eq!(all.env.get("APP").map(String::as_str), Some("all"));
+        assert_eq!(all.env.get("LANG").map(String::as_str), Some("C"));
+        assert_eq!(all.init_commands, ["unset HISTFILE", "sudo -iu app"]);
+
+        let BeelogError::Config(message) = resolve_node_group(groups, "a").unwrap_err() else { panic!() };
+        assert!(message.contains("a -> b -> a"), "{}", message);
+    }
+
+    #[test]
+    fn test_node_inventory_select() {
+        let config: Config = toml::from_str(r#"
+            [server.node-labels]
+            "web-[1-3]" = { role = "web", dc = "sh" }
+            "web-3" = { dc = "bj" }
+            "db-1" = { role = "db", dc = "sh" }
+            "cache-1" = { role = "cache", dc = "sh" }
+            "orphan-1" = { role = "cache" }
+            [[server.node-groups]]
+            group = "web"
+            nodes = ["web-[1-3]"]
+            [[server.node-groups]]
+            group = "db"
+            nodes = ["db-1"]
+            [[server.node-groups]]
+            group = "other"
+            nodes = ["cache-1"]
+            servers = ["test"]
+        "#).unwrap();
+        let config = config.server;
+        let inventory = NodeInventory::new("prod", config.node_groups, &config.node_labels).unwrap();
+        assert_eq!(inventory.labels("web-3").and_then(|labels| labels.get("dc")).map(String::as_str), Some("bj"));
+        assert_eq!(inventory.all_nodes().unwrap().nodes, ["db-1", "web-1", "web-2", "web-3"]);
+
+        let selected = inventory.select("dc=sh", None).unwrap();
+        assert_eq!(selected.group, "dc=sh");
+        assert_eq!(selected.nodes, ["db-1", "web-1", "web-2"]);
+        let selected = inventory.select("role=web and dc!=sh", Some("web")).unwrap();
+        assert_eq!((selected.group.as_str(), selected.nodes.as_slice()), ("web[role=web and dc!=sh]", ["web-3".to_string()].as_slice()));
+        let selection = NodeSelection { group: Some("web".to_string()), select: None };
+        assert_eq!(inventory.resolve(&selection).unwrap().nodes, ["web-1", "web-2", "web-3"]);
+        assert_eq!(inventory.group_names(), ["web", "db"]);
+        assert!(inventory.select("role=cache", None).is_err());
+        let test = NodeInventory::new("test", inventory.groups.clone(), &config.node_labels).unwrap();
+        assert_eq!(test.select("role=cache", None).unwrap().nodes, ["cache-1"]);
+        assert!(inventory.select("role=", None).is_err());
+    }
+}
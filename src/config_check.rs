@@ -0,0 +1,385 @@
+//! 配置文件检查及有效配置展示
+
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::Table;
+use toml_edit::{Item, TableLike};
+use crate::config::{self, ConfigSource};
+use crate::error::BeelogError;
+use crate::i18n::Msg;
+use crate::mfa;
+use crate::node_pattern;
+use crate::tr;
+
+/// 展示时替换敏感字段的内容
+const MASK: &str = "******";
+/// 可能为明文的敏感字段
+const SECRET_SOURCE_KEYS: &[&str] = &["password", "key_passphrase"];
+/// 始终为明文的敏感字段
+const SECRET_KEYS: &[&str] = &["secret_code"];
+/// 非明文的密钥来源前缀
+const SECRET_SOURCE_PREFIXES: &[&str] = &["env:", "file:", "cmd:"];
+
+/// 诊断级别
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str(&tr!(SeverityError)),
+            Severity::Warning => f.write_str(&tr!(SeverityWarning)),
+        }
+    }
+}
+
+/// 配置问题，定位到文件及行列号（从 1 开始）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+
+    pub fn error(message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            file: None,
+            line: None,
+            column: None,
+            message,
+        }
+    }
+
+    pub fn warning(message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message)
+        }
+    }
+
+    /// 记录所在文件，span 为 content 中的字节范围
+    pub fn at(mut self, file: &Path, content: &str, span: Option<Range<usize>>) -> Self {
+        self.file = Some(file.to_path_buf());
+        if let Some(span) = span {
+            let before = &content[..span.start.min(content.len())];
+            let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+            self.line = Some(before.matches('\n').count() + 1);
+            self.column = Some(before[line_start..].chars().count() + 1);
+        }
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+            if let (Some(line), Some(column)) = (self.line, self.column) {
+                write!(f, ":{}:{}", line, column)?;
+            }
+            f.write_str(": ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/**
+ * 检查配置文件
+ * 包括语法及字段、default-server / default-node-group 是否存在且匹配、同一文件中的重复名称、
+ * node group 引用的 server 是否存在、include、节点范围及节点标签的范围是否有效、私钥文件是否可读、secret_code 是否为有效的 base32 编码，以及明文密码、空节点组等警告
+ * 语法或字段错误时无法继续检查，只返回该错误
+ */
+pub fn check_config(path: &Path) -> Vec<Diagnostic> {
+    let sources = match config::load_sources(path) {
+        Ok(sources) => sources,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+    let server_config = match config::parse_sources(path, &sources) {
+        Ok(config) => config.server,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+    let locator = Locator::new(&sources);
+    let mut diagnostics = Vec::new();
+
+    for (document, source) in locator.documents.iter().zip(&sources) {
+        for (key, name_key, msg) in [
+            ("servers", "name", Msg::DuplicateServer),
+            ("node-groups", "group", Msg::DuplicateNodeGroup),
+        ] {
+            let mut seen = HashSet::new();
+            let server = document.as_ref().and_then(|doc| doc.get("server")).and_then(Item::as_table_like);
+            for entry in server.and_then(|server| server.get(key)).map(entries).unwrap_or_default() {
+                let Some(name) = entry.get(name_key) else { continue };
+                if let Some(value) = name.as_str() && !seen.insert(value.to_string()) {
+                    diagnostics.push(Diagnostic::error(msg.format(&[&value]))
+                        .at(&source.path, &source.content, name.span()));
+                }
+            }
+        }
+    }
+
+    if server_config.servers.is_empty() {
+        diagnostics.push(Diagnostic::error(tr!(CheckNoServers)).at(path, "", None));
+    }
+    let server_exists = |name: &str| server_config.servers.iter().any(|server| server.name == name);
+    if let Some(default_server) = &server_config.default_server
+        && !server_exists(default_server) {
+        diagnostics.push(locator.locate(
+            Diagnostic::error(tr!(CheckDefaultServerMissing, default_server)),
+            |server| server.get("default-server"),
+        ));
+    }
+    if let Some(default_group) = &server_config.default_node_group {
+        match server_config.node_groups.iter().find(|group| &group.group == default_group) {
+            None => diagnostics.push(locator.locate(
+                Diagnostic::error(tr!(CheckDefaultGroupMissing, default_group)),
+                |server| server.get("default-node-group"),
+            )),
+            Some(group) => if let Some(default_server) = &server_config.default_server
+                && !group.applies_to(default_server) {
+                diagnostics.push(locator.locate(
+                    Diagnostic::error(tr!(CheckDefaultGroupScope, default_group, default_server)),
+                    |server| server.get("default-node-group"),
+                ));
+            },
+        }
+    }
+
+    for server in &server_config.servers {
+        if let Some(key_path) = &server.key_path {
+            let path = config::expand_path(key_path);
+            if let Err(e) = fs::File::open(&path) {
+                diagnostics.push(locator.locate(
+                    Diagnostic::error(tr!(CheckKeyUnreadable, server.name, path.display(), e)),
+                    |table| server_field(table, &server.name, "key_path"),
+                ));
+            }
+        }
+        if let Some(secret_code) = &server.secret_code
+            && mfa::get_google_code(secret_code).is_err() {
+            diagnostics.push(locator.locate(
+                Diagnostic::error(tr!(CheckInvalidSecret, server.name)),
+                |table| server_field(table, &server.name, "secret_code"),
+            ));
+        }
+        for (key, value) in [("password", &server.password), ("key_passphrase", &server.key_passphrase)] {
+            if value.as_deref().is_some_and(is_plaintext) {
+                diagnostics.push(locator.locate(
+                    Diagnostic::warning(tr!(CheckPlaintextSecret, server.name, key)),
+                    |table| server_field(table, &server.name, key),
+                ));
+            }
+        }
+    }
+
+    for pattern in server_config.node_labels.keys() {
+        if let Err(BeelogError::Config(message)) = node_pattern::expand(pattern) {
+            diagnostics.push(locator.locate(
+                Diagnostic::error(message),
+                |server| server.get("node-labels").and_then(Item::as_table_like).and_then(|labels| labels.get(pattern)),
+            ));
+        }
+    }
+
+    // include 链上的错误会在多个节点组中出现，只报告一次
+    let mut resolve_errors = HashSet::new();
+    for group in &server_config.node_groups {
+        let name = group.group.as_str();
+        match config::resolve_node_group(&server_config.node_groups, name) {
+            // nodes_cmd 的节点在选择服务器后才获取，不检查
+            Ok(resolved) if resolved.nodes.is_empty() && group.nodes_cmd.is_none() => diagnostics.push(locator.locate(
+                Diagnostic::warning(tr!(CheckEmptyGroup, name)),
+                |table| find_field(table, "node-groups", "group", name, "nodes"),
+            )),
+            Ok(_) => {}
+            Err(e) => {
+                let message = match e {
+                    BeelogError::Config(message) => message,
+                    e => e.to_string(),
+                };
+                if resolve_errors.insert(message.clone()) {
+                    diagnostics.push(locator.locate(
+                        Diagnostic::error(message),
+                        |table| find_field(table, "node-groups", "group", name, "group"),
+                    ));
+                }
+            }
+        }
+        for server in group.servers.iter().filter(|server| !server_exists(server)) {
+            diagnostics.push(locator.locate(
+                Diagnostic::error(tr!(CheckUnknownGroupServer, name, server)),
+                |table| find_field(table, "node-groups", "group", name, "servers"),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/**
+ * 合并后的有效配置，敏感字段替换为 `******`
+ * password / key_passphrase 为 env: / file: / cmd: 来源时保留原样，便于确认来源
+ */
+pub fn effective_config(sources: &[ConfigSource]) -> Table {
+    let mut merged = config::merge_sources(sources);
+    let servers = merged.get_mut("server")
+        .and_then(|server| server.get_mut("servers"))
+        .and_then(|servers| servers.as_array_mut());
+    for server in servers.into_iter().flatten().filter_map(|server| server.as_table_mut()) {
+        for (key, value) in server.iter_mut() {
+            let masked = match value.as_str() {
+                Some(_) if SECRET_KEYS.contains(&key.as_str()) => true,
+                Some(source) => SECRET_SOURCE_KEYS.contains(&key.as_str()) && is_plaintext(source),
+                None => false,
+            };
+            if masked {
+                *value = toml::Value::String(MASK.to_string());
+            }
+        }
+    }
+    merged
+}
+
+/// 是否为明文密钥
+fn is_plaintext(source: &str) -> bool {
+    !SECRET_SOURCE_PREFIXES.iter().any(|prefix| source.starts_with(prefix))
+}
+
+/// 各配置文件保留位置信息的解析结果，用于定位诊断
+struct Locator<'a> {
+    sources: &'a [ConfigSource],
+    documents: Vec<Option<toml_edit::Document<&'a str>>>,
+}
+
+impl<'a> Locator<'a> {
+
+    fn new(sources: &'a [ConfigSource]) -> Self {
+        let documents = sources.iter()
+            .map(|source| toml_edit::Document::parse(source.content.as_str()).ok())
+            .collect();
+        Locator { sources, documents }
+    }
+
+    /// 在优先级最高的、定义了该字段的文件中定位，find 参数为 `[server]` 表
+    fn locate<F>(&self, diagnostic: Diagnostic, find: F) -> Diagnostic
+    where
+        F: Fn(&dyn TableLike) -> Option<&Item>,
+    {
+        let found = self.documents.iter().zip(self.sources).rev().find_map(|(document, source)| {
+            let server = document.as_ref()?.get("server")?.as_table_like()?;
+            find(server).map(|item| (source, item.span()))
+        });
+        match found {
+            Some((source, span)) => diagnostic.at(&source.path, &source.content, span),
+            None => diagnostic,
+        }
+    }
+}
+
+/// 按名称查找数组条目中的字段，同名条目以最后定义该字段的为准
+fn find_field<'t>(table: &'t dyn TableLike, key: &str, name_key: &str, name: &str, field: &str) -> Option<&'t Item> {
+    table.get(key).map(entries).unwrap_or_default().into_iter().rev()
+        .filter(|entry| entry.get(name_key).and_then(Item::as_str) == Some(name))
+        .find_map(|entry| entry.get(field))
+}
+
+/// 指定 server 中的字段
+fn server_field<'t>(table: &'t dyn TableLike, name: &str, field: &str) -> Option<&'t Item> {
+    find_field(table, "servers", "name", name, field)
+}
+
+/// `[[x]]` 或内联数组中的各个表
+fn entries(item: &Item) -> Vec<&dyn TableLike> {
+    if let Some(tables) = item.as_array_of_tables() {
+        tables.iter().map(|table| table as &dyn TableLike).collect()
+    } else if let Some(array) = item.as_array() {
+        array.iter().filter_map(|value| value.as_inline_table()).map(|table| table as &dyn TableLike).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_check_config() {
+        let dir = TempDir::new("check");
+        let path = dir.join("config.toml");
+        fs::write(&path, r#"[server]
+default-server = "prod"
+default-node-group = "missing"
+
+[[server.servers]]
+name = "prod"
+host = "10.0.0.1"
+port = 2222
+user = "alice"
+key_path = "/nonexistent/id_rsa"
+password = "plain"
+secret_code = "not base32!"
+
+[[server.servers]]
+name = "prod"
+host = "10.0.0.2"
+port = 2222
+user = "alice"
+
+[[server.node-groups]]
+group = "app"
+nodes = ["app1"]
+"#).unwrap();
+
+        let diagnostics = check_config(&path);
+        let located: Vec<_> = diagnostics.iter()
+            .map(|d| (d.severity, d.line, d.column))
+            .collect();
+        assert_eq!(located, [
+            (Severity::Error, Some(15), Some(8)),
+            (Severity::Error, Some(3), Some(22)),
+            (Severity::Error, Some(10), Some(12)),
+            (Severity::Error, Some(12), Some(15)),
+            (Severity::Warning, Some(11), Some(12)),
+        ]);
+
+        fs::write(&path, "[server]\ndefault-server = \"prod\"\nservers = 1\n").unwrap();
+        let diagnostics = check_config(&path);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file.as_deref(), Some(path.as_path()));
+        assert!(diagnostics[0].line.is_some());
+
+        fs::write(&path, "[server\n").unwrap();
+        assert_eq!(check_config(&path)[0].line, Some(1));
+    }
+
+    #[test]
+    fn test_effective_config_masks_secrets() {
+        let table: Table = toml::from_str(r#"
+            [[server.servers]]
+            name = "prod"
+            password = "plain"
+            key_passphrase = "env:KEY_PASS"
+            secret_code = "JBSWY3DPEHPK3PXP"
+        "#).unwrap();
+        let sources = [ConfigSource { path: PathBuf::from("config.toml"), content: String::new(), table }];
+        let effective = effective_config(&sources);
+        let server = &effective["server"]["servers"][0];
+        assert_eq!(server["password"].as_str(), Some(MASK));
+        assert_eq!(server["key_passphrase"].as_str(), Some("env:KEY_PASS"));
+        assert_eq!(server["secret_code"].as_str(), Some(MASK));
+        assert_eq!(server["name"].as_str(), Some("prod"));
+    }
+}
@@ -0,0 +1,257 @@
+use beelog::args::ConfigCommand;
+use beelog::config::{self, ConfigSource};
+use beelog::config_check::{self, Severity};
+use beelog::i18n;
+use beelog::mfa;
+use beelog::tr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use toml::Value;
+
+/// 未输入时使用的默认值
+const DEFAULT_SERVER_NAME: &str = "jumpserver";
+const DEFAULT_PORT: &str = "2222";
+const DEFAULT_KEY_PATH: &str = "~/.ssh/id_rsa";
+const DEFAULT_GROUP: &str = "default";
+/// 未使用私钥时默认从该环境变量读取登录密码
+const DEFAULT_PASSWORD_SOURCE: &str = "env:BEELOG_PASSWORD";
+/// 登录密码来源的前缀
+const SECRET_SOURCE_PREFIXES: &[&str] = &["env:", "file:", "cmd:"];
+
+/**
+ * 执行 config 子命令
+ * return 进程退出码
+ */
+pub fn run(command: &ConfigCommand, config: Option<&Path>) -> i32 {
+    let path = match config::get_config_file_path(config) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    apply_language(&path);
+    match command {
+        ConfigCommand::Init { force } => init(&path, *force),
+        ConfigCommand::Check => check(&path),
+        ConfigCommand::Show => show(&path),
+    }
+}
+
+/// 按配置中的 language 输出，配置有误时保持当前语言
+pub fn apply_language(path: &Path) {
+    if let Ok(config) = config::load_sources(path).and_then(|sources| config::parse_sources(path, &sources))
+        && let Some(locale) = config.server.language {
+        i18n::set_locale(locale);
+    }
+}
+
+/// 交互式生成配置文件，完成后检查一次
+fn init(path: &Path, force: bool) -> i32 {
+    if path.exists() && !force {
+        println!("{}", tr!(InitExists, path.display()));
+        return 1;
+    }
+    let content = match ask_config() {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            println!("{}", tr!(InitAborted));
+            return 1;
+        }
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    if let Some(dir) = path.parent()
+        && let Err(e) = fs::create_dir_all(dir) {
+        println!("{}", tr!(WriteFileFailed, dir.display(), e));
+        return 1;
+    }
+    if let Err(e) = write_private(path, &content) {
+        println!("{}", tr!(WriteFileFailed, path.display(), e));
+        return 1;
+    }
+    println!("{}", tr!(InitWritten, path.display()));
+    check(path)
+}
+
+/**
+ * 依次询问各项配置并生成配置文件内容
+ * 输入结束（Ctrl-D）时返回 None
+ */
+fn ask_config() -> io::Result<Option<String>> {
+    let Some(name) = ask(&tr!(InitServerName), Some(DEFAULT_SERVER_NAME))? else { return Ok(None) };
+    let Some(host) = ask(&tr!(InitHost), None)? else { return Ok(None) };
+    let port = loop {
+        let Some(port) = ask(&tr!(InitPort), Some(DEFAULT_PORT))? else { return Ok(None) };
+        match port.parse::<u16>() {
+            Ok(port) => break port,
+            Err(_) => println!("⚠️ {}", tr!(InitInvalidPort, port)),
+        }
+    };
+    let user_default = std::env::var("USER").ok().filter(|user| !user.is_empty());
+    let Some(user) = ask(&tr!(InitUser), user_default.as_deref())? else { return Ok(None) };
+    let key_default = config::expand_path(DEFAULT_KEY_PATH).exists().then_some(DEFAULT_KEY_PATH);
+    let Some(key_path) = ask_optional(&tr!(InitKeyPath), key_default)? else { return Ok(None) };
+    let Some(password) = ask_password(key_path.is_none())? else { return Ok(None) };
+    let secret_code = loop {
+        let secret_code = Some(read_secret(&tr!(InitSecretCode))?).filter(|code| !code.is_empty());
+        match secret_code.as_deref().map(mfa::get_google_code) {
+            Some(Err(e)) => println!("⚠️ {}", e),
+            _ => break secret_code,
+        }
+    };
+    let Some(group) = ask(&tr!(InitGroup), Some(DEFAULT_GROUP))? else { return Ok(None) };
+    let Some(nodes) = ask(&tr!(InitNodes), None)? else { return Ok(None) };
+    let nodes: Vec<Value> = nodes.split([',', ' '])
+        .filter(|node| !node.is_empty())
+        .map(|node| Value::String(node.to_string()))
+        .collect();
+
+    let quote = |value: &str| Value::String(value.to_string()).to_string();
+    let mut content = format!("# {}\n\n[server]\n", tr!(InitHeader));
+    content.push_str(&format!("default-server = {}\n", quote(&name)));
+    content.push_str(&format!("default-node-group = {}\n", quote(&group)));
+    content.push_str("\n[[server.servers]]\n");
+    content.push_str(&format!("name = {}\nhost = {}\nport = {}\nuser = {}\n", quote(&name), quote(&host), port, quote(&user)));
+    if let Some(key_path) = &key_path {
+        content.push_str(&format!("key_path = {}\n", quote(key_path)));
+    }
+    if let Some(password) = &password {
+        content.push_str(&format!("password = {}\n", quote(password)));
+    }
+    if let Some(secret_code) = &secret_code {
+        content.push_str(&format!("secret_code = {}\n", quote(secret_code)));
+    }
+    // 未使用私钥时不尝试证书认证
+    if key_path.is_none() {
+        let methods = if password.is_some() { r#"["password", "keyboard-interactive"]"# } else { r#"["keyboard-interactive"]"# };
+        content.push_str(&format!("auth_methods = {}\n", methods));
+    }
+    content.push_str("\n[[server.node-groups]]\n");
+    content.push_str(&format!("group = {}\nnodes = {}\n", quote(&group), Value::Array(nodes)));
+    Ok(Some(content))
+}
+
+/// 询问必填项，有默认值时直接回车使用默认值；输入结束时返回 None
+fn ask(label: &str, default: Option<&str>) -> io::Result<Option<String>> {
+    loop {
+        match read_answer(label, default)? {
+            None => return Ok(None),
+            Some(answer) if !answer.is_empty() => return Ok(Some(answer)),
+            Some(_) => match default {
+                Some(default) => return Ok(Some(default.to_string())),
+                None => println!("⚠️ {}", tr!(InitRequired)),
+            },
+        }
+    }
+}
+
+/// 询问可选项，外层 None 表示输入结束，内层 None 表示跳过
+fn ask_optional(label: &str, default: Option<&str>) -> io::Result<Option<Option<String>>> {
+    Ok(read_answer(label, default)?.map(|answer| match answer.is_empty() {
+        true => default.map(str::to_string),
+        false => Some(answer),
+    }))
+}
+
+/**
+ * 询问登录密码来源，外层 None 表示输入结束，内层 None 表示跳过
+ * 默认使用 env: 来源；明文密码需输入 `!` 后不回显地输入，避免显示在终端上
+ */
+fn ask_password(default_source: bool) -> io::Result<Option<Option<String>>> {
+    let default = default_source.then_some(DEFAULT_PASSWORD_SOURCE);
+    loop {
+        let Some(answer) = ask_optional(&tr!(InitPassword), default)? else { return Ok(None) };
+        match answer.as_deref() {
+            None | Some("-") => return Ok(Some(None)),
+            Some("!") => {
+                let password = read_secret(&tr!(InitPasswordInput))?;
+                if !password.is_empty() {
+                    return Ok(Some(Some(password)));
+                }
+            }
+            Some(source) if SECRET_SOURCE_PREFIXES.iter().any(|prefix| source.starts_with(prefix)) => return Ok(Some(answer)),
+            Some(_) => println!("⚠️ {}", tr!(InitPasswordPlain)),
+        }
+    }
+}
+
+/// 不回显地读取一行，用于密码等敏感内容
+fn read_secret(label: &str) -> io::Result<String> {
+    rpassword::prompt_password(format!("{}: ", label)).map(|answer| answer.trim().to_string())
+}
+
+/// 写入配置文件，unix 上只允许当前用户读写
+fn write_private(path: &Path, content: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // 覆盖已存在的文件时 mode 不生效，需单独修改
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+/// 输出提示并读取一行
+fn read_answer(label: &str, default: Option<&str>) -> io::Result<Option<String>> {
+    match default {
+        Some(default) => print!("{} [{}]: ", label, default),
+        None => print!("{}: ", label),
+    }
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// 检查配置文件并输出诊断，存在错误时返回 1
+fn check(path: &Path) -> i32 {
+    let diagnostics = config_check::check_config(path);
+    for diagnostic in &diagnostics {
+        println!("{}: {}", diagnostic.severity, diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    if diagnostics.is_empty() {
+        println!("{}", tr!(CheckPassed, path.display()));
+    } else {
+        println!("{}", tr!(CheckSummary, errors, diagnostics.len() - errors));
+    }
+    if errors > 0 { 1 } else { 0 }
+}
+
+/// 输出合并后的有效配置
+fn show(path: &Path) -> i32 {
+    let sources: Vec<ConfigSource> = match config::load_sources(path) {
+        Ok(sources) => sources,
+        Err(diagnostic) => {
+            println!("{}: {}", diagnostic.severity, diagnostic);
+            return 1;
+        }
+    };
+    println!("# {}", tr!(ShowSources));
+    for source in &sources {
+        println!("#   {}", source.path.display());
+    }
+    let effective = config_check::effective_config(&sources);
+    match toml::to_string_pretty(&effective) {
+        Ok(content) => {
+            println!("\n{}", content);
+            0
+        }
+        Err(e) => {
+            println!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! 修改配置文件
+//!
+//! 基于 toml_edit 编辑原文，保留注释、键的顺序及格式，修改后配置文件仍可手工编辑。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, TableLike};
+use crate::config::{self, NodeGroup};
+use crate::error::{BeelogError, Result};
+use crate::tr;
+
+/// 节点组中由 [`ConfigEditor::set_node_group`] 管理的字段，替换时删除新节点组中没有的字段
+const NODE_GROUP_FIELDS: &[&str] = &[
+    "nodes", "include", "exclude", "expected_hosts", "servers", "nodes_cmd", "nodes_cmd_timeout", "nodes_cmd_ttl",
+];
+
+/// 单个配置文件的编辑器，修改在 [`ConfigEditor::save`] 后写入
+pub struct ConfigEditor {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl ConfigEditor {
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| BeelogError::Config(tr!(ReadFileFailed, path.display(), e)))?;
+        let document = content.parse()
+            .map_err(|e| BeelogError::Config(tr!(ParseFileFailed, path.display(), e)))?;
+        Ok(ConfigEditor { path: path.to_path_buf(), document })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /**
+     * 将条目追加到 server 下的数组，如 servers、node-groups
+     * 原文使用 [[server.xxx]] 或尚未定义时追加为表，使用内联数组时追加为内联表；条目中的表写为内联表
+     */
+    pub fn append_entry(&mut self, key: &str, entry: &Table) -> Result<()> {
+        let table = to_edit_table(entry)?;
+        match self.entries_mut(key)? {
+            Item::ArrayOfTables(array) => array.push(table),
+            Item::Value(toml_edit::Value::Array(array)) => array.push(table.into_inline_table()),
+            _ => return Err(BeelogError::Config(tr!(ConfigEntryInvalidType, key))),
+        }
+        Ok(())
+    }
+
+    /**
+     * 添加节点组，本文件中已有同名节点组时在原位置替换其节点等字段，保留该条目的注释
+     * return 是否替换了已有的节点组
+     */
+    pub fn set_node_group(&mut self, group: &NodeGroup) -> Result<bool> {
+        let entry = node_group_table(group);
+        let Some(existing) = self.find_node_group(&group.group)? else {
+            self.append_entry("node-groups", &entry)?;
+            return Ok(false);
+        };
+        let table = to_edit_table(&entry)?;
+        for field in NODE_GROUP_FIELDS {
+            if !table.contains_key(field) {
+                existing.remove(field);
+            }
+        }
+        for (key, item) in table.iter() {
+            match (existing.get_mut(key), item) {
+                (Some(Item::Value(value)), Item::Value(new)) => {
+                    // 保留原有的前后空白及注释
+                    let decor = value.decor().clone();
+                    *value = new.clone();
+                    *value.decor_mut() = decor;
+                }
+                _ => {
+                    existing.insert(key, item.clone());
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// 删除本文件中的节点组，return 是否存在
+    pub fn remove_node_group(&mut self, name: &str) -> Result<bool> {
+        let matches = |table: &dyn TableLike| table.get("group").and_then(Item::as_str) == Some(name);
+        let Some(item) = self.server_mut()?.get_mut("node-groups") else {
+            return Ok(false);
+        };
+        match item {
+            Item::ArrayOfTables(array) => {
+                let before = array.len();
+                array.retain(|table| !matches(table));
+                Ok(array.len() != before)
+            }
+            Item::Value(toml_edit::Value::Array(array)) => {
+                let before = array.len();
+                array.retain(|value| !value.as_inline_table().is_some_and(|table| matches(table)));
+                Ok(array.len() != before)
+            }
+            _ => Err(BeelogError::Config(tr!(ConfigEntryInvalidType, "node-groups"))),
+        }
+    }
+
+    /// 写入配置文件
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.path, self.document.to_string())
+            .map_err(|e| BeelogError::Config(tr!(WriteFileFailed, self.path.display(), e)))
+    }
+
+    fn server_mut(&mut self) -> Result<&mut dyn TableLike> {
+        self.document.entry("server")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| BeelogError::Config(tr!(ConfigEntryInvalidType, "server")))
+    }
+
+    fn entries_mut(&mut self, key: &str) -> Result<&mut Item> {
+        Ok(self.server_mut()?.entry(key).or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new())))
+    }
+
+    fn find_node_group(&mut self, name: &str) -> Result<Option<&mut dyn TableLike>> {
+        let Some(item) = self.server_mut()?.get_mut("node-groups") else {
+            return Ok(None);
+        };
+        let found: Option<&mut dyn TableLike> = match item {
+            Item::ArrayOfTables(array) => array.iter_mut()
+                .find(|table| table.get("group").and_then(Item::as_str) == Some(name))
+                .map(|table| table as &mut dyn TableLike),
+            Item::Value(toml_edit::Value::Array(array)) => array.iter_mut()
+                .filter_map(toml_edit::Value::as_inline_table_mut)
+                .find(|table| table.get("group").and_then(toml_edit::Value::as_str) == Some(name))
+                .map(|table| table as &mut dyn TableLike),
+            _ => return Err(BeelogError::Config(tr!(ConfigEntryInvalidType, "node-groups"))),
+        };
+        Ok(found)
+    }
+}
+
+/// 合并后的配置中是否已有同名节点组，包括 extends、conf.d 及外部清单中的节点组
+pub fn node_group_exists(path: &Path, name: &str) -> bool {
+    config::load_sources(path)
+        .map(|sources| config::merge_sources(&sources))
+        .ok()
+        .and_then(|merged| merged.get("server")?.get("node-groups")?.as_array().cloned())
+        .is_some_and(|groups| groups.iter().any(|entry| entry.get("group").and_then(Value::as_str) == Some(name)))
+}
+
+/// 节点组写入配置文件的字段，省略空字段
+fn node_group_table(group: &NodeGroup) -> Table {
+    let strings = |values: &[String]| Value::Array(values.iter().cloned().map(Value::String).collect());
+    let mut table = Table::new();
+    table.insert("group".to_string(), Value::String(group.group.clone()));
+    table.insert("nodes".to_string(), strings(&group.nodes));
+    for (key, values) in [("include", &group.include), ("exclude", &group.exclude), ("servers", &group.servers)] {
+        if !values.is_empty() {
+            table.insert(key.to_string(), strings(values));
+        }
+    }
+    if !group.expected_hosts.is_empty() {
+        // 按节点顺序写入，便于比对
+        let expected_hosts = group.nodes.iter()
+            .filter_map(|node| group.expected_hosts.get(node).map(|host| (node.clone(), Value::String(host.clone()))))
+            .collect();
+        table.insert("expected_hosts".to_string(), Value::Table(expected_hosts));
+    }
+    // 初始化配置只在有值时写入，替换已有节点组时不删除其原有配置
+    if let Some(working_dir) = &group.working_dir {
+        table.insert("working_dir".to_string(), Value::String(working_dir.clone()));
+    }
+    if !group.env.is_empty() {
+        let env = group.env.iter().map(|(key, value)| (key.clone(), Value::String(value.clone()))).collect();
+        table.insert("env".to_string(), Value::Table(env));
+    }
+    if !group.init_commands.is_empty() {
+        table.insert("init_commands".to_string(), strings(&group.init_commands));
+    }
+    table
+}
+
+/// 转换为 toml_edit 的表，嵌套的表转换为内联表
+fn to_edit_table(entry: &Table) -> Result<toml_edit::Table> {
+    let parsed = toml::to_string(entry)
+        .map_err(|e| BeelogError::Config(e.to_string()))?
+        .parse::<DocumentMut>()
+        .map_err(|e| BeelogError::Config(e.to_string()))?;
+    let mut table = toml_edit::Table::new();
+    for (key, item) in parsed.iter() {
+        let item = match item {
+            Item::Table(nested) => Item::Value(nested.clone().into_inline_table().into()),
+            item => item.clone(),
+        };
+        table.insert(key, item);
+    }
+    Ok(table)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_config_editor() {
+        let dir = TempDir::new("config-edit");
+        let path = dir.join("config.toml");
+        fs::write(&path, r#"# 团队配置
+[server]
+default-server = "prod"
+
+# web 节点
+[[server.node-groups]]
+group = "web"
+nodes = ["web1"] # 旧节点
+include = ["base"]
+
+[[server.node-groups]]
+group = "db"
+nodes = ["db1"]
+"#).unwrap();
+
+        let mut editor = ConfigEditor::open(&path).unwrap();
+        let web = NodeGroup { group: "web".into(), nodes: vec!["web1".into(), "web2".into()], ..NodeGroup::default() };
+        assert!(editor.set_node_group(&web).unwrap());
+        let mut adhoc = NodeGroup { group: "adhoc".into(), nodes: vec!["a1".into()], servers: vec!["prod".into()], ..NodeGroup::default() };
+        adhoc.expected_hosts.insert("a1".into(), "10.0.0.1".into());
+        assert!(!editor.set_node_group(&adhoc).unwrap());
+        assert!(editor.remove_node_group("db").unwrap());
+        assert!(!editor.remove_node_group("missing").unwrap());
+        editor.save().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"# 团队配置
+[server]
+default-server = "prod"
+
+# web 节点
+[[server.node-groups]]
+group = "web"
+nodes = ["web1", "web2"] # 旧节点
+
+[[server.node-groups]]
+group = "adhoc"
+nodes = ["a1"]
+servers = ["prod"]
+expected_hosts = { a1 = "10.0.0.1" }
+"#);
+    }
+
+    #[test]
+    fn test_save_literal_nodes() {
+        let dir = TempDir::new("config-edit-literal");
+        let path = dir.join("config.toml");
+        fs::write(&path, "[server]\n").unwrap();
+        let nodes = ["a\\b", "odd[1]", "web,01"];
+        let group = NodeGroup {
+            group: "saved".into(),
+            nodes: nodes.iter().map(|node| crate::node_pattern::escape(node)).collect(),
+            ..NodeGroup::default()
+        };
+        let mut editor = ConfigEditor::open(&path).unwrap();
+        editor.set_node_group(&group).unwrap();
+        editor.save().unwrap();
+
+        // 重新读取后得到相同的节点
+        let config = config::load_sources(&path).and_then(|sources| config::parse_sources(&path, &sources)).unwrap();
+        assert_eq!(config::resolve_node_group(&config.server.node_groups, "saved").unwrap().nodes, nodes);
+    }
+}
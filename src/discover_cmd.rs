@@ -0,0 +1,108 @@
+use beelog::asset::Asset;
+use beelog::config::{self, NodeGroup};
+use beelog::config_edit::{self, ConfigEditor};
+use beelog::jump_server_helper::Helper;
+use beelog::node_pattern;
+use beelog::tr;
+use std::path::Path;
+use std::sync::Arc;
+use crate::config_cmd;
+use crate::terminal::TerminalInteraction;
+
+/**
+ * 执行 discover 子命令：登录服务器列出可访问的资产并输出
+ * 指定 save 时将资产的主机名保存为新的节点组，IP 作为 expected_hosts，只适用于该服务器
+ * return 进程退出码
+ */
+pub async fn run(server: Option<&str>, filter: Option<&str>, save: Option<&str>, config: Option<&Path>) -> i32 {
+    let path = match config::get_config_file_path(config) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    config_cmd::apply_language(&path);
+    let server_info = match config::read_server_info(Some(&path), server, &TerminalInteraction) {
+        Ok(server_info) => server_info,
+        Err(e) => {
+            println!("{}", tr!(ReadConfigFailed, e));
+            return 1;
+        }
+    };
+    if let Some(group) = save && config_edit::node_group_exists(&path, group) {
+        println!("{}", tr!(DiscoverGroupExists, group));
+        return 1;
+    }
+    let server_name = server_info.name.clone();
+    let mut assets = match Helper::discover(server_info, filter, Arc::new(TerminalInteraction)).await {
+        Ok(assets) => assets,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    if assets.is_empty() {
+        println!("{}", tr!(DiscoverNone));
+        return if save.is_some() { 1 } else { 0 };
+    }
+    print_assets(&assets);
+    println!("{}", tr!(DiscoverSummary, assets.len()));
+
+    match save {
+        Some(group) => {
+            assets.sort_by(|a, b| node_pattern::natural_cmp(&a.hostname, &b.hostname));
+            save_group(&path, group, &server_name, &assets)
+        }
+        None => 0,
+    }
+}
+
+/// 按列对齐输出资产
+fn print_assets(assets: &[Asset]) {
+    let header = tr!(DiscoverHeader);
+    let rows: Vec<Vec<&str>> = std::iter::once(header.split('\t').collect())
+        .chain(assets.iter().map(|asset| vec![asset.id.as_str(), &asset.hostname, &asset.ip, &asset.comment]))
+        .collect();
+    let mut widths = [0; 4];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let line: Vec<String> = row.iter().zip(widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+/// 将资产保存为节点组
+fn save_group(path: &Path, group: &str, server: &str, assets: &[Asset]) -> i32 {
+    let node_group = NodeGroup {
+        group: group.to_string(),
+        // 主机名原样保存，其中的 `[` 不作为范围
+        nodes: assets.iter().map(|asset| node_pattern::escape(&asset.hostname)).collect(),
+        expected_hosts: assets.iter()
+            .filter(|asset| !asset.ip.is_empty())
+            .map(|asset| (asset.hostname.clone(), asset.ip.clone()))
+            .collect(),
+        servers: vec![server.to_string()],
+        ..NodeGroup::default()
+    };
+    let saved = ConfigEditor::open(path).and_then(|mut editor| {
+        editor.set_node_group(&node_group)?;
+        editor.save()
+    });
+    match saved {
+        Ok(()) => {
+            println!("{}", tr!(DiscoverSaved, assets.len(), group, path.display()));
+            0
+        }
+        Err(e) => {
+            println!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::{self, Display};
+use thiserror::Error;
+use crate::i18n::{self, Locale, Msg};
+
+
+/// beelog 的错误类型
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum BeelogError {
+    /// 配置文件或凭据来源有误
+    Config(String),
+    /// 服务器地址解析失败
+    Resolve(String),
+    /// 网络连接、握手或开启通道失败
+    Connect(String),
+    /// 用户认证或服务器公钥校验失败
+    Auth(String),
+    /// 进入节点或节点校验失败
+    Navigation(String),
+    /// 等待输出超时
+    Timeout(String),
+    /// 通道或会话已关闭
+    ChannelClosed(String),
+    /// 内容解码失败
+    Decode(String),
+}
+
+/// 按当前语言输出错误信息
+impl Display for BeelogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message_in(i18n::locale()))
+    }
+}
+
+pub type Result<T, E = BeelogError> = std::result::Result<T, E>;
+
+impl BeelogError {
+
+    /// 指定语言的错误信息
+    pub fn message_in(&self, locale: Locale) -> String {
+        let (msg, message) = match self {
+            BeelogError::Config(m) => (Msg::ErrConfig, m),
+            BeelogError::Resolve(m) => (Msg::ErrResolve, m),
+            BeelogError::Connect(m) => (Msg::ErrConnect, m),
+            BeelogError::Auth(m) => (Msg::ErrAuth, m),
+            BeelogError::Navigation(m) => (Msg::ErrNavigation, m),
+            BeelogError::Timeout(m) => (Msg::ErrTimeout, m),
+            BeelogError::ChannelClosed(m) => (Msg::ErrChannelClosed, m),
+            BeelogError::Decode(m) => (Msg::ErrDecode, m),
+        };
+        msg.format_in(locale, &[message])
+    }
+
+    /// 是否为可恢复的错误，如网络抖动、超时，连接时可重试
+    pub fn is_transient(&self) -> bool {
+        matches!(self, BeelogError::Connect(_) | BeelogError::Timeout(_) | BeelogError::ChannelClosed(_))
+    }
+
+    /// 在错误信息前添加上下文，保持错误类型不变
+    pub fn context(self, context: impl Display) -> Self {
+        let wrap = |message: String| format!("{}: {}", context, message);
+        match self {
+            BeelogError::Config(m) => BeelogError::Config(wrap(m)),
+            BeelogError::Resolve(m) => BeelogError::Resolve(wrap(m)),
+            BeelogError::Connect(m) => BeelogError::Connect(wrap(m)),
+            BeelogError::Auth(m) => BeelogError::Auth(wrap(m)),
+            BeelogError::Navigation(m) => BeelogError::Navigation(wrap(m)),
+            BeelogError::Timeout(m) => BeelogError::Timeout(wrap(m)),
+            BeelogError::ChannelClosed(m) => BeelogError::ChannelClosed(wrap(m)),
+            BeelogError::Decode(m) => BeelogError::Decode(wrap(m)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context() {
+        let error = BeelogError::Timeout("进入节点 node1".to_string()).context("重试 3 次后失败");
+        assert!(error.is_transient());
+        assert_eq!(error.message_in(Locale::ZhCn), "超时: 重试 3 次后失败: 进入节点 node1");
+        assert!(!BeelogError::Auth("密码错误".to_string()).is_transient());
+    }
+}
@@ -0,0 +1,85 @@
+use beelog::args::GroupCommand;
+use beelog::config::{self, NodeGroup};
+use beelog::config_edit::{self, ConfigEditor};
+use beelog::node_pattern;
+use beelog::tr;
+use std::path::Path;
+
+/**
+ * 执行 group 子命令
+ * return 进程退出码
+ */
+pub fn run(command: &GroupCommand, config: Option<&Path>) -> i32 {
+    let path = match config::get_config_file_path(config) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    crate::config_cmd::apply_language(&path);
+    let result = match command {
+        GroupCommand::Add { name, nodes, server, force } => add(&path, name, nodes, server, *force),
+        GroupCommand::Remove { name } => remove(&path, name),
+    };
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            println!("{}", e);
+            1
+        }
+    }
+}
+
+/// 添加节点组，节点支持逗号分隔，范围原样写入
+fn add(path: &Path, name: &str, nodes: &[String], servers: &[String], force: bool) -> Result<String, String> {
+    let nodes: Vec<String> = nodes.iter()
+        .flat_map(|nodes| nodes.split(','))
+        .map(str::trim)
+        .filter(|node| !node.is_empty())
+        .map(str::to_string)
+        .collect();
+    for node in &nodes {
+        node_pattern::expand(node).map_err(|e| e.to_string())?;
+    }
+    let group = NodeGroup {
+        group: name.to_string(),
+        nodes,
+        servers: servers.to_vec(),
+        ..NodeGroup::default()
+    };
+    save(path, &group, force)
+}
+
+/**
+ * 将节点组写入配置文件，REPL 的 :save-group 共用
+ * 合并后的配置中已有同名节点组且未指定 force 时不写入
+ * return 输出的提示信息
+ */
+pub fn save(path: &Path, group: &NodeGroup, force: bool) -> Result<String, String> {
+    if !force && config_edit::node_group_exists(path, &group.group) {
+        return Err(tr!(GroupExists, group.group));
+    }
+    let mut editor = ConfigEditor::open(path).map_err(|e| e.to_string())?;
+    let replaced = editor.set_node_group(group).map_err(|e| e.to_string())?;
+    editor.save().map_err(|e| e.to_string())?;
+    // 范围按展开后的节点计数
+    let count: usize = group.nodes.iter().map(|node| node_pattern::expand(node).map_or(1, |nodes| nodes.len())).sum();
+    Ok(match replaced {
+        true => tr!(GroupReplaced, group.group, count, path.display()),
+        false => tr!(GroupAdded, group.group, count, path.display()),
+    })
+}
+
+/// 删除配置文件本身中的节点组
+fn remove(path: &Path, name: &str) -> Result<String, String> {
+    let mut editor = ConfigEditor::open(path).map_err(|e| e.to_string())?;
+    if !editor.remove_node_group(name).map_err(|e| e.to_string())? {
+        return Err(tr!(GroupNotInFile, path.display(), name));
+    }
+    editor.save().map_err(|e| e.to_string())?;
+    Ok(tr!(GroupRemoved, name, path.display()))
+}
@@ -0,0 +1,390 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+
+/// 界面语言
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum Locale {
+    /// 简体中文
+    #[default]
+    #[serde(rename = "zh-CN", alias = "zh")]
+    #[value(name = "zh-CN", alias = "zh")]
+    ZhCn,
+    /// English
+    #[serde(rename = "en")]
+    #[value(name = "en")]
+    En,
+}
+
+/// 当前语言，未设置时为简体中文
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::ZhCn as u8);
+
+impl Locale {
+
+    /**
+     * 解析 LANG 等环境变量的取值，如 `zh_CN.UTF-8`、`en_US.UTF-8`
+     * `C`、`POSIX` 等未指定语言的取值返回 None
+     */
+    pub fn from_env_value(value: &str) -> Option<Locale> {
+        let language = value.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match language {
+            "" | "C" | "POSIX" => None,
+            "zh" => Some(Locale::ZhCn),
+            _ => Some(Locale::En),
+        }
+    }
+}
+
+/// 设置当前语言
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// 当前语言
+pub fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        x if x == Locale::En as u8 => Locale::En,
+        _ => Locale::ZhCn,
+    }
+}
+
+/// 按 LC_ALL、LC_MESSAGES、LANG 的顺序读取语言设置，均未指定时保持默认
+pub fn init_from_env() {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| Locale::from_env_value(&value));
+    if let Some(locale) = locale {
+        set_locale(locale);
+    }
+}
+
+/**
+ * 翻译消息，参数依次替换模板中的 `{}`
+ *      tr!(ConfigNotFound, path.display())
+ */
+#[macro_export]
+macro_rules! tr {
+    ($msg:ident) => {
+        $crate::i18n::Msg::$msg.text().to_string()
+    };
+    ($msg:ident, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::Msg::$msg.format(&[$(&$arg as &dyn ::std::fmt::Display),+])
+    };
+}
+
+/// 定义消息及各语言的模板
+macro_rules! catalog {
+    ($($name:ident => $zh:literal, $en:literal;)*) => {
+        /// 界面消息
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum Msg {
+            $($name,)*
+        }
+
+        impl Msg {
+            #[cfg(test)]
+            const ALL: &[Msg] = &[$(Msg::$name,)*];
+
+            /// 指定语言的模板
+            pub fn text_in(self, locale: Locale) -> &'static str {
+                match (self, locale) {
+                    $(
+                        (Msg::$name, Locale::ZhCn) => $zh,
+                        (Msg::$name, Locale::En) => $en,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+impl Msg {
+
+    /// 当前语言的模板
+    pub fn text(self) -> &'static str {
+        self.text_in(locale())
+    }
+
+    /// 当前语言的模板，参数依次替换 `{}`，多余的参数忽略
+    pub fn format(self, args: &[&dyn Display]) -> String {
+        self.format_in(locale(), args)
+    }
+
+    /// 指定语言的模板，参数依次替换 `{}`，多余的参数忽略
+    pub fn format_in(self, locale: Locale, args: &[&dyn Display]) -> String {
+        let mut args = args.iter();
+        let mut parts = self.text_in(locale).split("{}");
+        let mut message = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                message.push_str(&arg.to_string());
+            }
+            message.push_str(part);
+        }
+        message
+    }
+}
+
+catalog! {
+    // 命令行参数
+    CliAbout => "收集jumpserver日志", "Collect logs through JumpServer";
+    CliServer => "指定server配置name", "Name of the server in the configuration";
+    CliNodeGroup => "指定节点分组配置name", "Name of the node group in the configuration";
+    CliParallel => "同时连接的节点数上限", "Maximum number of nodes to connect at the same time";
+    CliFirstCome => "按完成顺序输出各节点结果，默认全部结束后按节点顺序输出", "Print each node's result as soon as it finishes instead of in node order";
+    CliFormat => "输出格式：banner 按节点分块，prefix 每行添加节点前缀", "Output format: banner groups output by node, prefix adds the node name to every line";
+    CliFormatBanner => "每个节点一个 `======node=======` 块", "one `======node=======` block per node";
+    CliFormatPrefix => "每行添加节点前缀 `node: line`，便于 grep/sort", "prefix every line with `node: `, handy for grep/sort";
+    CliSelect => "按标签选择节点，如 'role=web and dc!=bj'，未指定节点组时从服务器的所有节点中选择", "Select nodes by label, e.g. 'role=web and dc!=bj'; without a node group, selects from all nodes of the server";
+    CliConfig => "配置文件路径，默认读取 BEELOG_CONFIG 或 ~/.config/beelog/config.toml", "Configuration file path, defaults to BEELOG_CONFIG or ~/.config/beelog/config.toml";
+    CliVerbose => "输出调试日志，-v 记录连接及认证过程，-vv 额外记录收到的原始输出", "Debug logging: -v records connection and authentication, -vv also records raw output";
+    CliLogFile => "调试日志写入文件而不是标准错误，未指定 -v 时按 -v 记录", "Write debug logs to a file instead of stderr, implies -v";
+    CliHelp => "显示帮助", "Print help";
+    CliVersion => "显示版本", "Print version";
+    CliHelpCommand => "显示帮助，或指定子命令的帮助", "Print this message or the help of the given subcommand(s)";
+    CliConfigCommand => "管理配置文件", "Manage the configuration file";
+    CliConfigInit => "交互式生成配置文件", "Create a configuration file interactively";
+    CliConfigInitForce => "覆盖已存在的配置文件", "Overwrite an existing configuration file";
+    CliConfigCheck => "检查配置文件", "Check the configuration file";
+    CliConfigShow => "显示合并后的有效配置，敏感字段已隐藏", "Print the effective merged configuration with secrets masked";
+    CliImport => "将外部清单中的 server 及 node group 写入配置文件", "Write servers and node groups from inventories into the configuration file";
+    CliImportSources => "清单路径，未指定时使用配置中的 inventory；ansible: / ssh: 前缀指定类型", "Inventory paths, defaults to inventory in the configuration; prefix with ansible: / ssh: to set the type";
+    CliImportDryRun => "只显示将导入的内容，不写入配置文件", "Only show what would be imported without writing the configuration file";
+    CliGroupCommand => "管理配置文件中的节点组", "Manage node groups in the configuration file";
+    CliGroupAdd => "添加节点组，保留配置文件的注释及格式", "Add a node group, keeping comments and formatting of the configuration file";
+    CliGroupRemove => "删除配置文件中的节点组", "Remove a node group from the configuration file";
+    CliGroupName => "节点组名称", "Node group name";
+    CliGroupNodes => "节点，支持范围及逗号分隔", "Nodes, ranges and comma-separated lists are supported";
+    CliGroupServer => "适用的服务器名称，可多次指定", "Server the group applies to, can be repeated";
+    CliGroupForce => "覆盖已存在的节点组", "Overwrite an existing node group";
+    CliDiscover => "通过JumpServer菜单列出可访问的资产", "List accessible assets through the JumpServer menu";
+    CliDiscoverServer => "指定server配置name，未指定时使用默认服务器", "Server name from the configuration, defaults to default-server";
+    CliDiscoverFilter => "搜索关键字，未指定时列出所有资产", "Search keyword, lists all assets when omitted";
+    CliDiscoverSave => "将列出的资产保存为新的节点组", "Save the listed assets as a new node group";
+
+    // 错误类型
+    ErrConfig => "配置错误: {}", "configuration error: {}";
+    ErrResolve => "地址解析失败: {}", "failed to resolve address: {}";
+    ErrConnect => "连接失败: {}", "connection failed: {}";
+    ErrAuth => "认证失败: {}", "authentication failed: {}";
+    ErrNavigation => "进入节点失败: {}", "failed to enter node: {}";
+    ErrTimeout => "超时: {}", "timed out: {}";
+    ErrChannelClosed => "通道已关闭: {}", "channel closed: {}";
+    ErrDecode => "解码失败: {}", "decode failed: {}";
+
+    // 配置
+    ConfigNotFound => "配置文件未找到 {}", "configuration file not found: {}";
+    ServerNotFound => "未找到server配置: {}", "server not found in configuration: {}";
+    NodeGroupNotFound => "未找到node group配置: {}", "node group not found in configuration: {}";
+    DuplicateServer => "server 名称重复: {}", "duplicate server name: {}";
+    DuplicateNodeGroup => "node group 名称重复: {}", "duplicate node group name: {}";
+    NodeGroupNotForServer => "node group {} 不适用于 server {}", "node group {} is not available for server {}";
+    GroupIncludeCycle => "node group 存在循环 include: {}", "node groups include each other in a cycle: {}";
+    GroupIncludeUnknown => "node group {} include 的分组不存在: {}", "node group {} includes an unknown group: {}";
+    NodeRangeInvalid => "节点范围无效: {}，格式如 app-[01-20]", "invalid node range: {}, expected e.g. app-[01-20]";
+    NodeRangeTooLarge => "节点范围 {} 展开后超过 {} 个节点", "node range {} expands to more than {} nodes";
+    SelectUnexpected => "选择表达式中的 `{}` 无效: {}", "unexpected `{}` in selector: {}";
+    SelectIncomplete => "选择表达式不完整: {}", "incomplete selector: {}";
+    SelectUnclosedQuote => "选择表达式中的引号未闭合: {}", "unclosed quote in selector: {}";
+    SelectNoNodes => "没有匹配 {} 的节点", "no nodes match {}";
+    ChooseServer => "选择 server", "Choose a server";
+    ChooseNodeGroup => "选择 server {} 的 node group", "Choose a node group for server {}";
+    ServerNotSelected => "未选择 server，请通过 -s 指定或配置 default-server", "no server selected, use -s or set default-server";
+    NodeGroupNotSelected => "未选择 server {} 的 node group，请通过 -n 指定或配置 default-node-group", "no node group selected for server {}, use -n or set default-node-group";
+    NoHomeDir => "无法确定用户主目录，请通过 --config 或 BEELOG_CONFIG 指定配置文件", "unable to determine the home directory, use --config or BEELOG_CONFIG to specify the configuration file";
+    ConfigExtendsCycle => "配置文件 {} 存在循环 extends", "configuration file {} has circular extends";
+    ConfigExtendsInvalid => "{} 中的 extends 必须是路径或路径数组", "extends in {} must be a path or an array of paths";
+    NodesCmdFailed => "node group {} 的 nodes_cmd 执行失败: {}", "nodes_cmd of node group {} failed: {}";
+    NodesCmdTimeout => "node group {} 的 nodes_cmd 超过 {} 秒未结束", "nodes_cmd of node group {} did not finish within {} seconds";
+    NodesCmdInvalidJson => "node group {} 的 nodes_cmd 输出不是有效的节点列表: {}", "nodes_cmd of node group {} printed an invalid node list: {}";
+    InventoryInvalid => "{} 中的 inventory 必须是路径或路径数组", "inventory in {} must be a path or an array of paths";
+    InventoryHostRange => "主机范围无效: {}，仅支持数字范围，如 web[01:20]", "invalid host range: {}, only numeric ranges such as web[01:20] are supported";
+    InventoryInvalidLine => "无法解析的清单行: {}", "unrecognized inventory line: {}";
+    InventoryYamlGroup => "清单分组 {} 必须是包含 hosts / children 的映射", "inventory group {} must be a mapping with hosts / children";
+    ReadFileFailed => "读取 {} 失败: {}", "failed to read {}: {}";
+    ParseFileFailed => "解析 {} 失败: {}", "failed to parse {}: {}";
+    WriteFileFailed => "写入 {} 失败: {}", "failed to write {}: {}";
+    LogFileOpenFailed => "打开日志文件 {} 失败: {}", "failed to open log file {}: {}";
+    SeverityError => "错误", "error";
+    SeverityWarning => "警告", "warning";
+    CheckNoServers => "没有配置 server", "no servers are configured";
+    CheckUnknownGroupServer => "node group {} 引用的 server 不存在: {}", "node group {} refers to an unknown server: {}";
+    CheckDefaultGroupScope => "default-node-group {} 不适用于 default-server {}", "default-node-group {} is not available for default-server {}";
+    CheckDefaultServerMissing => "default-server 指定的 server 不存在: {}", "default-server refers to an unknown server: {}";
+    CheckDefaultGroupMissing => "default-node-group 指定的 node group 不存在: {}", "default-node-group refers to an unknown node group: {}";
+    CheckKeyUnreadable => "server {} 的私钥 {} 无法读取: {}", "server {}: private key {} is not readable: {}";
+    CheckInvalidSecret => "server {} 的 secret_code 不是有效的 base32 编码", "secret_code of server {} is not valid base32";
+    CheckPlaintextSecret => "server {} 的 {} 为明文，建议改用 env: / file: / cmd:", "server {}: {} is in plain text, consider env: / file: / cmd:";
+    CheckEmptyGroup => "node group {} 没有节点", "node group {} has no nodes";
+    ClientNoServer => "未设置服务器", "no server is set";
+    ClientNoNodes => "未设置节点", "no nodes are set";
+
+    // 认证
+    AuthNoPassphrase => "未提供私钥 {} 的密码", "no passphrase provided for private key {}";
+    AuthPublickeyFailed => "证书认证失败: {}", "public key authentication failed: {}";
+    AuthNoKeyPath => "未配置 key_path", "key_path is not configured";
+    AuthPasswordFailed => "密码认证失败: {}", "password authentication failed: {}";
+    AuthNoPassword => "未配置 password", "password is not configured";
+    AuthInteractiveFailed => "二次认证失败: {}", "keyboard-interactive authentication failed: {}";
+    AuthInteractiveUnanswered => "二次认证失败: {}，无法应答的提示: {}", "keyboard-interactive authentication failed: {}, unanswered prompts: {}";
+    AgentInitFailed => "ssh-agent 初始化失败: {}", "failed to initialize ssh-agent: {}";
+    AgentConnectFailed => "ssh-agent 连接失败: {}", "failed to connect to ssh-agent: {}";
+    AgentListFailed => "ssh-agent 读取身份失败: {}", "failed to list ssh-agent identities: {}";
+    AgentNoIdentity => "ssh-agent 认证失败: 无可用身份", "ssh-agent authentication failed: no usable identity";
+    SecretEnvFailed => "读取环境变量 {} 失败: {}", "failed to read environment variable {}: {}";
+    SecretFileFailed => "读取文件 {} 失败: {}", "failed to read file {}: {}";
+    SecretCmdFailed => "执行命令失败: {}", "command failed: {}";
+    KeyReadFailed => "读取私钥 {} 失败: {}", "failed to read private key {}: {}";
+    KeyInvalid => "私钥 {} 格式错误: {}", "invalid private key {}: {}";
+    InvalidSecretCode => "secret_code 不是有效的 base32 编码", "secret_code is not valid base32";
+
+    // 服务器公钥
+    HostKeyUnavailable => "无法获取服务器公钥", "unable to get the server host key";
+    HostKeyNoFingerprint => "无法计算服务器公钥指纹", "unable to compute the host key fingerprint";
+    HostKeyCheckFailed => "校验服务器公钥失败", "host key verification failed";
+    HostKeyCheckError => "校验服务器公钥失败: {}", "host key verification failed: {}";
+    HostKeyMismatch => "服务器 {}:{} 的公钥与 {} 中记录的不一致，可能存在中间人攻击！当前指纹 {}", "the host key of {}:{} does not match the one recorded in {}, possible man-in-the-middle attack! Current fingerprint {}";
+    HostKeyNotRecorded => "服务器 {}:{} 的公钥未在 {} 中记录，指纹 {}", "the host key of {}:{} is not recorded in {}, fingerprint {}";
+    HostKeyNotTrusted => "未信任服务器 {}:{} 的公钥，指纹 {}", "the host key of {}:{} was not trusted, fingerprint {}";
+    HostKeyUnknown => "服务器 {}:{} 的公钥未知", "the host key of {}:{} is unknown";
+    HostKeyFingerprint => "{} 公钥指纹为 {}", "{} key fingerprint is {}";
+    HostKeyCannotConfirm => "非交互终端，无法确认该公钥；可在终端中先连接一次，或设置 host_key_policy = \"accept-new-unattended\"", "not an interactive terminal, unable to confirm the key; connect once from a terminal or set host_key_policy = \"accept-new-unattended\"";
+    HostKeyConfirm => "是否信任并继续连接? (yes/no): ", "Trust this key and continue connecting? (yes/no): ";
+    PassphrasePrompt => "请输入私钥 {} 的密码: ", "Enter passphrase for private key {}: ";
+
+    // 连接
+    NoAddress => "{}: 没有可用的地址", "{}: no usable address";
+    SessionCreateFailed => "创建 session 失败: {}", "failed to create session: {}";
+    HandshakeFailed => "握手失败: {}", "handshake failed: {}";
+    MenuMismatch => "未能正确连接", "did not reach the JumpServer menu";
+    WaitMenu => "等待菜单", "waiting for the menu";
+    ListAssets => "列出资产", "listing assets";
+    ChannelUnavailable => "通道已不可用", "the channel is no longer available";
+    SessionDisconnected => "会话已断开", "the session was disconnected";
+    DisconnectFailed => "断开会话失败: {}", "failed to disconnect session: {}";
+    ChannelOpenFailed => "创建 channel 失败: {}", "failed to open channel: {}";
+    PtyFailed => "PTY 请求失败: {}", "PTY request failed: {}";
+    ShellFailed => "打开 shell 失败: {}", "failed to start shell: {}";
+    ChannelCloseFailed => "关闭通道失败: {}", "failed to close channel: {}";
+    WriteFailed => "写入失败: {}", "write failed: {}";
+    ReadFailed => "读取失败: {}", "read failed: {}";
+    RetriesExhausted => "重试 {} 次后失败", "failed after {} retries";
+    EnterNode => "进入节点 {}", "entering node {}";
+    VerifyNotEntered => "节点校验失败: 未进入节点 {}, 实际输出: {}", "node verification failed: did not enter node {}, actual output: {}";
+    VerifyMismatch => "节点校验失败: 期望 {}, 实际输出: {}", "node verification failed: expected {}, actual output: {}";
+    InitCommandTimeout => "节点 {} 执行初始化命令超时: {}", "timed out running init command on {}: {}";
+    InitCommandFailed => "节点 {} 初始化命令失败（退出码 {}）: {}", "init command failed on {} (exit status {}): {}";
+
+    // 配置管理
+    InitServerName => "server 名称", "Server name";
+    InitHost => "JumpServer 地址", "JumpServer host";
+    InitPort => "端口", "Port";
+    InitUser => "用户名", "User";
+    InitKeyPath => "私钥路径（留空不使用私钥）", "Private key path (leave empty to skip)";
+    InitPassword => "登录密码来源 env:NAME / file:path / cmd:command，输入 ! 直接输入密码，输入 - 跳过", "Password source env:NAME / file:path / cmd:command, ! to type the password, - to skip";
+    InitPasswordInput => "登录密码（不回显）", "Password (hidden)";
+    InitPasswordPlain => "请使用 env: / file: / cmd: 来源，或输入 ! 后不回显地输入密码", "use an env: / file: / cmd: source, or enter ! to type the password without echo";
+    InitSecretCode => "MFA 密钥 secret_code（不回显，留空跳过）", "MFA secret_code (hidden, leave empty to skip)";
+    InitGroup => "节点分组名称", "Node group name";
+    InitNodes => "节点列表，以逗号或空格分隔", "Nodes, separated by commas or spaces";
+    InitRequired => "不能为空", "a value is required";
+    InitInvalidPort => "无效的端口: {}", "invalid port: {}";
+    InitExists => "配置文件 {} 已存在，使用 --force 覆盖", "configuration file {} already exists, use --force to overwrite it";
+    InitAborted => "输入已结束，未生成配置文件", "input ended, no configuration file was written";
+    InitHeader => "beelog 配置文件，完整字段说明见 README", "beelog configuration, see the README for all fields";
+    InitWritten => "已生成配置文件 {}", "configuration file written to {}";
+    CheckPassed => "配置检查通过: {}", "configuration is valid: {}";
+    CheckSummary => "{} 个错误，{} 个警告", "{} error(s), {} warning(s)";
+    ShowSources => "合并自:", "merged from:";
+    ImportNoSources => "未指定清单，请在命令行指定或在配置中设置 inventory", "no inventory given, pass paths or set inventory in the configuration";
+    ImportServer => "+ server {}", "+ server {}";
+    ImportNodeGroup => "+ node group {}", "+ node group {}";
+    ImportSkipped => "跳过已存在的 {}", "skipped existing {}";
+    ImportSummary => "导入 {} 个 server、{} 个 node group 到 {}", "imported {} servers and {} node groups into {}";
+    GroupExists => "node group {} 已存在，使用 --force 覆盖", "node group {} already exists, use --force to overwrite";
+    GroupAdded => "已添加 node group {}（{} 个节点），写入 {}", "added node group {} ({} nodes) to {}";
+    GroupReplaced => "已覆盖 node group {}（{} 个节点），写入 {}", "replaced node group {} ({} nodes) in {}";
+    GroupRemoved => "已删除 node group {}，写入 {}", "removed node group {} from {}";
+    GroupNotInFile => "{} 中没有 node group {}，extends、conf.d 及清单中的节点组需在其所在文件中修改", "{} does not define node group {}; groups from extends, conf.d or inventories must be edited in their own files";
+    DiscoverHeader => "ID\t主机名\tIP\t备注", "ID\tHostname\tIP\tComment";
+    DiscoverGroupExists => "node group {} 已存在", "node group {} already exists";
+    DiscoverNone => "没有可访问的资产", "no accessible assets";
+    DiscoverSummary => "共 {} 个资产", "{} assets";
+    DiscoverSaved => "已将 {} 个节点保存为 node group {}，写入 {}", "saved {} nodes as node group {} in {}";
+    ConfigEntryInvalidType => "配置中的 {} 不是表或数组，无法追加", "{} in the configuration is not a table or an array";
+    ImportDryRun => "--dry-run: 未写入 {}", "--dry-run: {} not modified";
+
+    // 终端及 REPL
+    ReadConfigFailed => "读取配置异常: {}", "failed to read configuration: {}";
+    PrepareCredentialsFailed => "准备认证信息失败: {}", "failed to prepare credentials: {}";
+    ActionConnect => "连接失败", "connection failed";
+    ActionExec => "执行命令错误", "command failed";
+    ActionClose => "关闭失败", "close failed";
+    HistoryReadFailed => "历史文件读取异常: {}", "failed to read history file: {}";
+    HistorySearch => " ({}搜索: {}) ", " ({}search: {}) ";
+    InvalidTimeout => "无效的超时时间: {}", "invalid timeout: {}";
+    InvalidFormat => "无效的输出格式: {}，可选 banner / prefix", "invalid output format: {}, expected banner / prefix";
+    SaveGroupUsage => "用法: :save-group <名称> [--force]", "usage: :save-group <name> [--force]";
+    UnknownCommand => "未知命令: :{}", "unknown command: :{}";
+    CurrentTimeout => "当前命令超时: {}秒", "current command timeout: {}s";
+    TimeoutSet => "命令超时已设置为 {}秒", "command timeout set to {}s";
+    OrderFirstCome => "已切换为按完成顺序输出", "switched to first-come output";
+    OrderOrdered => "已切换为按节点顺序输出", "switched to node-ordered output";
+    CurrentFormat => "当前输出格式: {}", "current output format: {}";
+    FormatSet => "输出格式已设置为 {}", "output format set to {}";
+    NodesSelected => "已选择 {} 个节点: {}", "{} node(s) selected: {}";
+    ReloadInvalid => "配置有错误，未重新加载", "configuration has errors, not reloaded";
+    ReloadFailed => "重新加载配置失败: {}", "failed to reload configuration: {}";
+    ReloadUnchanged => "配置没有变化", "configuration unchanged";
+    ReloadGroupsAdded => "新增节点组: {}", "node groups added: {}";
+    ReloadGroupsRemoved => "移除节点组: {}", "node groups removed: {}";
+    ReloadServerChanged => "⚠️ 服务器 {} 的连接参数已修改，重新启动后生效", "⚠️ connection settings of server {} changed, restart to apply";
+    CommandBlocked => "⚠️ 命令 `{}` 被禁止执行：可能导致会话阻塞", "⚠️ command `{}` is blocked: it may hang the session";
+    Interrupting => "^C 正在中断命令...", "^C interrupting command...";
+    ConnectInterrupting => "^C 正在中断连接...", "^C interrupting connection...";
+    ConnectInterrupted => "连接已中断", "connection interrupted";
+    TimedOutNodes => "⚠️ 命令执行超时（{}秒）已中断: {}", "⚠️ command timed out ({}s) and was interrupted on: {}";
+    CancelledNodes => "⚠️ 命令已取消: {}", "⚠️ command cancelled on: {}";
+
+    // 进度显示
+    BarConnect => "服务器连接", "Connecting";
+    BarConnected => "连接完成!", "Connected!";
+    BarExec => "命令执行", "Executing";
+    BarClose => "关闭连接", "Closing";
+    BarClosed => "全部关闭!", "All closed!";
+    NodeRunning => "执行中", "running";
+    NodeReceived => "已接收 {}", "received {}";
+    NodeInterrupting => "正在中断... {}", "interrupting... {}";
+    NodeCompleted => "✔ 完成 {}", "✔ done {}";
+    NodeTimedOut => "✘ 超时 {}", "✘ timed out {}";
+    NodeCancelled => "✘ 已取消 {}", "✘ cancelled {}";
+    NodeFailed => "✘ 失败: {}", "✘ failed: {}";
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env_value() {
+        assert_eq!(Locale::from_env_value("zh_CN.UTF-8"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_env_value("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::from_env_value("de_DE"), Some(Locale::En));
+        assert_eq!(Locale::from_env_value("C.UTF-8"), None);
+        assert_eq!(Locale::from_env_value("POSIX"), None);
+    }
+
+    #[test]
+    fn test_catalog_placeholders() {
+        for msg in Msg::ALL {
+            let count = |locale| msg.text_in(locale).matches("{}").count();
+            assert_eq!(count(Locale::ZhCn), count(Locale::En), "{:?}", msg);
+        }
+        assert_eq!(Msg::RetriesExhausted.format_in(Locale::ZhCn, &[&3]), "重试 3 次后失败");
+        assert_eq!(Msg::RetriesExhausted.format_in(Locale::En, &[&3]), "failed after 3 retries");
+    }
+}
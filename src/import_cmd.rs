@@ -0,0 +1,98 @@
+use beelog::config::{self, ConfigSource};
+use beelog::config_edit::ConfigEditor;
+use beelog::inventory::InventorySource;
+use beelog::tr;
+use std::collections::HashSet;
+use std::path::Path;
+use toml::Table;
+use crate::config_cmd;
+
+/**
+ * 执行 import 子命令，将清单中配置文件尚未定义的 server 及 node group 追加到配置文件
+ * 已存在的同名项保持不变；保留原有的注释及格式
+ * return 进程退出码
+ */
+pub fn run(sources: &[String], dry_run: bool, config: Option<&Path>) -> i32 {
+    let path = match config::get_config_file_path(config) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    config_cmd::apply_language(&path);
+    let layers = match config::load_layers(&path) {
+        Ok(layers) => layers,
+        Err(diagnostic) => {
+            println!("{}: {}", diagnostic.severity, diagnostic);
+            return 1;
+        }
+    };
+    let merged = config::merge_sources(&layers);
+    let inventories: Result<Vec<ConfigSource>, _> = match sources {
+        [] => config::load_inventories(&path, &merged),
+        _ => sources.iter().map(|source| InventorySource::parse(source, Path::new("")).load()).collect(),
+    };
+    let inventories = match inventories {
+        Ok(inventories) if inventories.is_empty() => {
+            println!("{}", tr!(ImportNoSources));
+            return 1;
+        }
+        Ok(inventories) => inventories,
+        Err(diagnostic) => {
+            println!("{}: {}", diagnostic.severity, diagnostic);
+            return 1;
+        }
+    };
+    let mut editor = match ConfigEditor::open(&path) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+
+    let mut imported = [0, 0];
+    for (index, (key, name_key)) in [("servers", "name"), ("node-groups", "group")].into_iter().enumerate() {
+        let mut existing: HashSet<String> = entries(&merged, key)
+            .filter_map(|entry| entry.get(name_key).and_then(toml::Value::as_str).map(str::to_string))
+            .collect();
+        for entry in inventories.iter().flat_map(|inventory| entries(&inventory.table, key)) {
+            let Some(name) = entry.get(name_key).and_then(toml::Value::as_str) else { continue };
+            if !existing.insert(name.to_string()) {
+                println!("{}", tr!(ImportSkipped, name));
+                continue;
+            }
+            match key {
+                "servers" => println!("{}", tr!(ImportServer, name)),
+                _ => println!("{}", tr!(ImportNodeGroup, name)),
+            }
+            if let Err(e) = editor.append_entry(key, entry) {
+                println!("{}", e);
+                return 1;
+            }
+            imported[index] += 1;
+        }
+    }
+
+    if dry_run {
+        println!("{}", tr!(ImportDryRun, path.display()));
+        return 0;
+    }
+    if imported != [0, 0] && let Err(e) = editor.save() {
+        println!("{}", e);
+        return 1;
+    }
+    println!("{}", tr!(ImportSummary, imported[0], imported[1], path.display()));
+    0
+}
+
+/// server 表中指定数组的条目
+fn entries<'a>(table: &'a Table, key: &str) -> impl Iterator<Item = &'a Table> {
+    table.get("server")
+        .and_then(|server| server.get(key))
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_table)
+}
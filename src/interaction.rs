@@ -0,0 +1,39 @@
+use std::path::Path;
+
+
+/**
+ * 需要用户参与的交互
+ * 库本身不读写终端，由调用方实现，如命令行提示或图形界面
+ */
+pub trait Interaction: Send + Sync {
+
+    /// 首次连接时确认服务器公钥指纹，返回是否信任
+    fn confirm_host_key(&self, host: &str, port: u16, key_type: &str, fingerprint: &str) -> bool;
+
+    /// 输入加密私钥的密码，返回 None 表示不提供
+    fn key_passphrase(&self, key_path: &Path) -> Option<String>;
+
+    /// 在多个候选项中选择一个，返回其下标，None 表示取消
+    fn choose(&self, prompt: &str, options: &[String]) -> Option<usize>;
+}
+
+/**
+ * 非交互环境：不信任未知的服务器公钥，不提供私钥密码，不做选择
+ * 需要自动记录新公钥时使用 `host_key_policy = "accept-new-unattended"`
+ */
+pub struct NonInteractive;
+
+impl Interaction for NonInteractive {
+
+    fn confirm_host_key(&self, _host: &str, _port: u16, _key_type: &str, _fingerprint: &str) -> bool {
+        false
+    }
+
+    fn key_passphrase(&self, _key_path: &Path) -> Option<String> {
+        None
+    }
+
+    fn choose(&self, _prompt: &str, _options: &[String]) -> Option<usize> {
+        None
+    }
+}
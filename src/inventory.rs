@@ -0,0 +1,438 @@
+//! 外部主机清单
+//!
+//! 支持 Ansible INI / YAML 清单及 ssh 配置文件：
+//! Ansible 的分组转换为节点组，`ansible_host` 作为节点校验的期望主机，子分组转换为 include；
+//! ssh 配置中的 `Host` 转换为服务器，`Host *` 中的配置作为其余 `Host` 的默认值。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use crate::config::{expand_path, ConfigSource};
+use crate::config_check::Diagnostic;
+use crate::tr;
+
+/// ssh 配置的默认端口
+const SSH_DEFAULT_PORT: i64 = 22;
+/// Ansible 中包含所有主机的分组，不转换为节点组
+const ANSIBLE_ALL: &str = "all";
+/// Ansible 中未分组主机所在的分组
+const ANSIBLE_UNGROUPED: &str = "ungrouped";
+
+/// 清单类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum InventorySource {
+    /// Ansible 清单，`.yml` / `.yaml` 按 YAML 解析，其余按 INI 解析
+    Ansible(PathBuf),
+    /// ssh 配置文件
+    SshConfig(PathBuf),
+}
+
+impl InventorySource {
+
+    /**
+     * 解析清单来源，相对路径基于 base_dir
+     *      ansible:path    Ansible 清单
+     *      ssh:path        ssh 配置文件
+     *      path            文件名为 config / ssh_config 时按 ssh 配置，否则按 Ansible 清单
+     */
+    pub fn parse(source: &str, base_dir: &Path) -> Self {
+        let resolve = |path: &str| base_dir.join(expand_path(path));
+        if let Some(path) = source.strip_prefix("ansible:") {
+            InventorySource::Ansible(resolve(path))
+        } else if let Some(path) = source.strip_prefix("ssh:") {
+            InventorySource::SshConfig(resolve(path))
+        } else {
+            let path = resolve(source);
+            match path.file_name().and_then(|name| name.to_str()) {
+                Some("config" | "ssh_config") => InventorySource::SshConfig(path),
+                _ => InventorySource::Ansible(path),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            InventorySource::Ansible(path) | InventorySource::SshConfig(path) => path,
+        }
+    }
+
+    /**
+     * 读取清单，转换为与配置文件结构相同的表：
+     *      { server = { servers = [...], node-groups = [...] } }
+     * 解析错误带有所在文件及行列号
+     */
+    pub fn load(&self) -> Result<ConfigSource, Diagnostic> {
+        let path = self.path();
+        let content = fs::read_to_string(path)
+            .map_err(|e| Diagnostic::error(tr!(ReadFileFailed, path.display(), e)))?;
+        let (servers, groups) = match self {
+            InventorySource::SshConfig(_) => (parse_ssh_config(&content), Vec::new()),
+            InventorySource::Ansible(_) if is_yaml(path) => (Vec::new(), parse_ansible_yaml(&content)
+                .map_err(|(offset, message)| Diagnostic::error(message).at(path, &content, offset.map(|o| o..o)))?),
+            InventorySource::Ansible(_) => (Vec::new(), parse_ansible_ini(&content)
+                .map_err(|(offset, message)| Diagnostic::error(message).at(path, &content, Some(offset..offset)))?),
+        };
+
+        let mut server = Table::new();
+        if !servers.is_empty() {
+            server.insert("servers".to_string(), Value::Array(servers.into_iter().map(Value::Table).collect()));
+        }
+        if !groups.is_empty() {
+            let groups = groups.into_iter().map(AnsibleGroup::into_table).map(Value::Table).collect();
+            server.insert("node-groups".to_string(), Value::Array(groups));
+        }
+        let mut table = Table::new();
+        table.insert("server".to_string(), Value::Table(server));
+        Ok(ConfigSource { path: path.to_path_buf(), content, table })
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yml" || ext == "yaml")
+}
+
+/// Ansible 分组
+#[derive(Debug, Default, PartialEq)]
+struct AnsibleGroup {
+    name: String,
+    hosts: Vec<String>,
+    children: Vec<String>,
+    /// 主机的 ansible_host
+    addresses: BTreeMap<String, String>,
+}
+
+impl AnsibleGroup {
+
+    fn into_table(self) -> Table {
+        let mut table = Table::new();
+        table.insert("group".to_string(), Value::String(self.name));
+        let strings = |values: Vec<String>| Value::Array(values.into_iter().map(Value::String).collect());
+        table.insert("nodes".to_string(), strings(self.hosts));
+        if !self.children.is_empty() {
+            table.insert("include".to_string(), strings(self.children));
+        }
+        if !self.addresses.is_empty() {
+            let addresses = self.addresses.into_iter().map(|(host, address)| (host, Value::String(address))).collect();
+            table.insert("expected_hosts".to_string(), Value::Table(addresses));
+        }
+        table
+    }
+}
+
+/// 按名称获取分组，不存在时按出现顺序追加
+fn group_mut<'a>(groups: &'a mut Vec<AnsibleGroup>, name: &str) -> &'a mut AnsibleGroup {
+    match groups.iter().position(|group| group.name == name) {
+        Some(index) => &mut groups[index],
+        None => {
+            groups.push(AnsibleGroup { name: name.to_string(), ..AnsibleGroup::default() });
+            groups.last_mut().expect("group just pushed")
+        }
+    }
+}
+
+/// 添加主机，同名主机只保留一次
+fn add_host(group: &mut AnsibleGroup, host: String, address: Option<String>) {
+    if let Some(address) = address {
+        group.addresses.insert(host.clone(), address);
+    }
+    if !group.hosts.contains(&host) {
+        group.hosts.push(host);
+    }
+}
+
+/// 去掉 all 分组及空的 ungrouped 分组
+fn finish_groups(mut groups: Vec<AnsibleGroup>) -> Vec<AnsibleGroup> {
+    groups.retain(|group| group.name != ANSIBLE_ALL && !(group.name == ANSIBLE_UNGROUPED && group.hosts.is_empty()));
+    for group in &mut groups {
+        group.children.retain(|child| child != ANSIBLE_ALL);
+    }
+    groups
+}
+
+/**
+ * 转换 Ansible 的主机范围 `web[01:20]` 为 `web[01-20]`
+ * 不支持字母范围及步长
+ */
+fn convert_host_pattern(pattern: &str) -> Result<String, String> {
+    let mut converted = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(open) = rest.find('[') {
+        let close = rest[open..].find(']').map(|pos| open + pos)
+            .ok_or_else(|| tr!(InventoryHostRange, pattern))?;
+        let range: Vec<&str> = rest[open + 1..close].split(':').collect();
+        let [start, end] = range[..] else {
+            return Err(tr!(InventoryHostRange, pattern));
+        };
+        if start.is_empty() || end.is_empty() || !(start.bytes().chain(end.bytes()).all(|b| b.is_ascii_digit())) {
+            return Err(tr!(InventoryHostRange, pattern));
+        }
+        converted.push_str(&rest[..open]);
+        converted.push_str(&format!("[{}-{}]", start, end));
+        rest = &rest[close + 1..];
+    }
+    converted.push_str(rest);
+    Ok(converted)
+}
+
+/**
+ * 解析 Ansible INI 清单
+ * 支持 `[group]`、`[group:children]`，忽略 `[group:vars]`；错误返回所在行的字节偏移
+ */
+fn parse_ansible_ini(content: &str) -> Result<Vec<AnsibleGroup>, (usize, String)> {
+    enum Section {
+        Hosts(String),
+        Children(String),
+        Vars,
+    }
+    let mut groups = Vec::new();
+    let mut section = Section::Hosts(ANSIBLE_UNGROUPED.to_string());
+    let mut offset = 0;
+    for raw_line in content.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += raw_line.len();
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.strip_suffix(']')
+                .ok_or_else(|| (line_offset, tr!(InventoryInvalidLine, line)))?;
+            section = match header.split_once(':') {
+                None => Section::Hosts(header.to_string()),
+                Some((name, "children")) => Section::Children(name.to_string()),
+                Some((_, "vars")) => Section::Vars,
+                Some(_) => return Err((line_offset, tr!(InventoryInvalidLine, line))),
+            };
+            if let Section::Hosts(name) | Section::Children(name) = &section {
+                group_mut(&mut groups, name);
+            }
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let name = fields.next().unwrap_or_default();
+        match &section {
+            Section::Hosts(group) => {
+                let host = convert_host_pattern(name).map_err(|message| (line_offset, message))?;
+                let address = fields
+                    .filter_map(|field| field.split_once('='))
+                    .find(|(key, _)| *key == "ansible_host")
+                    .map(|(_, value)| value.trim_matches(['"', '\'']).to_string());
+                add_host(group_mut(&mut groups, group), host, address);
+            }
+            Section::Children(group) => {
+                let group = group_mut(&mut groups, group);
+                if !group.children.iter().any(|child| child == name) {
+                    group.children.push(name.to_string());
+                }
+            }
+            Section::Vars => {}
+        }
+    }
+    Ok(finish_groups(groups))
+}
+
+/**
+ * 解析 Ansible YAML 清单
+ *      all: { hosts: {...}, children: { web: { hosts: {...}, children: {...} } } }
+ * 错误返回所在位置的字节偏移
+ */
+fn parse_ansible_yaml(content: &str) -> Result<Vec<AnsibleGroup>, (Option<usize>, String)> {
+    let root: serde_yaml::Value = serde_yaml::from_str(content)
+        .map_err(|e| (e.location().map(|location| location.index()), e.to_string()))?;
+    let mut groups = Vec::new();
+    match root {
+        serde_yaml::Value::Mapping(mapping) => {
+            for (name, group) in &mapping {
+                collect_yaml_group(&mut groups, yaml_key(name)?, group)?;
+            }
+        }
+        serde_yaml::Value::Null => {}
+        _ => return Err((None, tr!(InventoryYamlGroup, ANSIBLE_ALL))),
+    }
+    // all 中直接定义的主机属于 ungrouped
+    if let Some(all) = groups.iter().position(|group| group.name == ANSIBLE_ALL) {
+        let hosts = std::mem::take(&mut groups[all].hosts);
+        let addresses = std::mem::take(&mut groups[all].addresses);
+        let ungrouped = group_mut(&mut groups, ANSIBLE_UNGROUPED);
+        for host in hosts {
+            let address = addresses.get(&host).cloned();
+            add_host(ungrouped, host, address);
+        }
+    }
+    Ok(finish_groups(groups))
+}
+
+fn yaml_key(key: &serde_yaml::Value) -> Result<&str, (Option<usize>, String)> {
+    key.as_str().ok_or_else(|| (None, tr!(InventoryYamlGroup, format!("{:?}", key))))
+}
+
+/// 解析一个分组及其子分组
+fn collect_yaml_group(groups: &mut Vec<AnsibleGroup>, name: &str, value: &serde_yaml::Value) -> Result<(), (Option<usize>, String)> {
+    group_mut(groups, name);
+    let group = match value {
+        serde_yaml::Value::Mapping(group) => group,
+        serde_yaml::Value::Null => return Ok(()),
+        _ => return Err((None, tr!(InventoryYamlGroup, name))),
+    };
+    if let Some(serde_yaml::Value::Mapping(hosts)) = group.get("hosts") {
+        for (host, vars) in hosts {
+            let host = convert_host_pattern(yaml_key(host)?).map_err(|message| (None, message))?;
+            let address = vars.get("ansible_host").and_then(serde_yaml::Value::as_str).map(str::to_string);
+            add_host(group_mut(groups, name), host, address);
+        }
+    }
+    if let Some(serde_yaml::Value::Mapping(children)) = group.get("children") {
+        for (child, child_group) in children {
+            let child = yaml_key(child)?;
+            let parent = group_mut(groups, name);
+            if !parent.children.iter().any(|c| c == child) {
+                parent.children.push(child.to_string());
+            }
+            collect_yaml_group(groups, child, child_group)?;
+        }
+    }
+    Ok(())
+}
+
+/**
+ * 解析 ssh 配置，每个不含通配符的 Host 别名转换为一个服务器
+ * 使用 HostName、Port、User、IdentityFile；未配置 User 时使用当前用户，无法确定时跳过该 Host
+ */
+fn parse_ssh_config(content: &str) -> Vec<Table> {
+    // (别名, 配置)，配置的键为小写
+    let mut blocks: Vec<(Vec<String>, BTreeMap<String, String>)> = Vec::new();
+    let mut in_match = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((key, value)) => (key.to_ascii_lowercase(), value.trim_start_matches([' ', '\t', '=']).trim()),
+            None => continue,
+        };
+        match key.as_str() {
+            "host" => {
+                in_match = false;
+                blocks.push((value.split_whitespace().map(str::to_string).collect(), BTreeMap::new()));
+            }
+            "match" => in_match = true,
+            _ if in_match => {}
+            _ => if let Some((_, options)) = blocks.last_mut() {
+                // 与 ssh 一致，先出现的配置生效
+                options.entry(key).or_insert_with(|| value.trim_matches('"').to_string());
+            },
+        }
+    }
+
+    let wildcard = |alias: &str| alias.contains(['*', '?', '!']);
+    let mut defaults = BTreeMap::new();
+    for (aliases, options) in &blocks {
+        if aliases.iter().any(|alias| alias == "*") {
+            for (key, value) in options {
+                defaults.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    let current_user = std::env::var("USER").ok().filter(|user| !user.is_empty());
+    let mut servers: Vec<Table> = Vec::new();
+    for (aliases, options) in &blocks {
+        for alias in aliases.iter().filter(|alias| !wildcard(alias)) {
+            if servers.iter().any(|server| server.get("name").and_then(Value::as_str) == Some(alias)) {
+                continue;
+            }
+            let option = |key: &str| options.get(key).or_else(|| defaults.get(key));
+            let Some(user) = option("user").cloned().or_else(|| current_user.clone()) else { continue };
+            let mut server = Table::new();
+            server.insert("name".to_string(), Value::String(alias.clone()));
+            server.insert("host".to_string(), Value::String(option("hostname").cloned().unwrap_or_else(|| alias.clone())));
+            let port = option("port").and_then(|port| port.parse().ok()).unwrap_or(SSH_DEFAULT_PORT);
+            server.insert("port".to_string(), Value::Integer(port));
+            server.insert("user".to_string(), Value::String(user));
+            if let Some(identity_file) = option("identityfile") {
+                server.insert("key_path".to_string(), Value::String(identity_file.clone()));
+            }
+            servers.push(server);
+        }
+    }
+    servers
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ansible_ini() {
+        let groups = parse_ansible_ini(r#"
+            bastion.example.com
+            [web]
+            web[01:03].example.com
+            web-canary ansible_host=10.0.0.9 ansible_user=ops
+            [db]
+            db1
+            [db:vars]
+            ansible_port=2222
+            [prod:children]
+            web
+            db
+        "#).unwrap();
+        let names: Vec<_> = groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(names, ["ungrouped", "web", "db", "prod"]);
+        assert_eq!(groups[1].hosts, ["web[01-03].example.com", "web-canary"]);
+        assert_eq!(groups[1].addresses.get("web-canary").map(String::as_str), Some("10.0.0.9"));
+        assert_eq!(groups[3].children, ["web", "db"]);
+
+        let (offset, _) = parse_ansible_ini("[web]\nweb[a:c]\n").unwrap_err();
+        assert_eq!(offset, 6);
+    }
+
+    #[test]
+    fn test_parse_ansible_yaml() {
+        let groups = parse_ansible_yaml(r#"
+all:
+  hosts:
+    bastion:
+  children:
+    web:
+      hosts:
+        web[01:02]:
+        web-canary:
+          ansible_host: 10.0.0.9
+    prod:
+      children:
+        web:
+"#).unwrap();
+        let names: Vec<_> = groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(names, ["web", "prod", "ungrouped"]);
+        assert_eq!(groups[0].hosts, ["web[01-02]", "web-canary"]);
+        assert_eq!(groups[1].children, ["web"]);
+        assert_eq!(groups[2].hosts, ["bastion"]);
+        assert!(parse_ansible_yaml("all: [").is_err());
+    }
+
+    #[test]
+    fn test_parse_ssh_config() {
+        let servers = parse_ssh_config(r#"
+            Host jump jump-alias
+                HostName 10.0.0.1
+                Port 2222
+                User alice
+            Host dev-*
+                User bob
+            Match host foo
+                User nobody
+            Host *
+                User carol
+                IdentityFile ~/.ssh/id_ed25519
+            Host plain
+        "#);
+        let summary: Vec<_> = servers.iter()
+            .map(|server| (server["name"].as_str().unwrap(), server["host"].as_str().unwrap(), server["port"].as_integer().unwrap(), server["user"].as_str().unwrap()))
+            .collect();
+        assert_eq!(summary, [("jump", "10.0.0.1", 2222, "alice"), ("jump-alias", "10.0.0.1", 2222, "alice"), ("plain", "plain", 22, "carol")]);
+        assert_eq!(servers[2]["key_path"].as_str(), Some("~/.ssh/id_ed25519"));
+    }
+}
@@ -1,140 +1,694 @@
-use std::collections::HashMap;
-use std::process::exit;
-use std::sync::{Arc, Mutex};
-use indicatif::{ProgressBar, ProgressStyle};
-use crate::config::ServerInfo;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use futures::{Stream, StreamExt};
+use futures::stream::FuturesUnordered;
+use tokio::sync::{Mutex, mpsc};
+use tokio_util::sync::CancellationToken;
+use crate::asset::{self, Asset};
+use crate::auth::Credentials;
+use crate::config::{NodeGroup, ServerInfo};
+use crate::error::{BeelogError, Result};
+use crate::interaction::Interaction;
+use crate::redact;
+use crate::tr;
 use crate::ssh_bridge::*;
 
 
 const JUMP_SERVER_MARK : &str = "Opt>";
+/// 资产列表分页时的提示符
+const ASSET_PAGE_MARK: &str = "[Host]>";
+/// 资产列表的下一页
+const ASSET_PAGE_NEXT: &str = "n";
+/// 资产列表最多读取的页数，避免分页信息异常时无限翻页
+const MAX_ASSET_PAGES: usize = 1000;
+/// 默认同时连接的节点数
+const DEFAULT_MAX_CONCURRENCY: usize = 16;
+/// 重试退避的初始间隔与上限
+const BACKOFF_BASE_MILLIS: u64 = 500;
+const BACKOFF_MAX_MILLIS: u64 = 8000;
+/// 节点校验的超时时间
+const VERIFY_TIMEOUT_SECS: u64 = 10;
+/// 校验输出的起止标记，命令中使用引号拆分，避免回显内容被误匹配
+const VERIFY_BEGIN: &str = "__BEELOG_VERIFY_BEGIN__";
+const VERIFY_END: &str = "__BEELOG_VERIFY_END__";
+const VERIFY_COMMAND: &str = "echo __BEELOG_''VERIFY_BEGIN__; hostname; hostname -I 2>/dev/null; echo __BEELOG_''VERIFY_END__";
+/// 初始化命令退出码的标记，每条命令结束后单独执行，切换用户等启动新 shell 的命令也能检查
+const INIT_STATUS_MARK: &str = "__BEELOG_INIT_STATUS__";
+const INIT_STATUS_COMMAND: &str = "echo __BEELOG_''INIT_STATUS__$?";
 
 pub struct Helper {
     jump_server_bridges: Vec<JumpServerBridge>,
+    /// 已认证的会话，多个节点共用，新增节点时继续使用
+    pool: SessionPool,
 }
 
+/// 命令执行状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecStatus {
+    Completed,
+    /// 超时，已发送 Ctrl-C 中断
+    TimedOut,
+    /// 被调用方取消，已发送 Ctrl-C 中断
+    Cancelled,
+}
+
+/// 单个节点的命令输出，包含命令回显及结尾的提示符
+#[derive(Debug, Clone)]
+pub struct NodeOutput {
+    pub status: ExecStatus,
+    pub output: String,
+}
+
+/// 单个节点的执行结果
+#[derive(Debug)]
+pub struct NodeResult {
+    pub node: String,
+    pub result: Result<NodeOutput>,
+}
+
+/**
+ * 连接、执行及关闭过程的进度回调
+ * 库本身不输出任何内容，由调用方实现进度显示；所有方法默认不做处理
+ */
+pub trait Progress: Send + Sync {
+
+    /// 开始连接节点
+    fn connect_started(&self, _nodes: &[String]) {}
+
+    /// 单个节点连接结束，失败时带有错误
+    fn node_connected(&self, _node: &str, _error: Option<&BeelogError>) {}
+
+    /// 所有节点连接结束
+    fn connect_finished(&self) {}
+
+    /// 开始执行命令
+    fn exec_started(&self, _nodes: &[String]) {}
+
+    /// 节点收到新的输出
+    fn node_output(&self, _node: &str, _chunk: &str) {}
+
+    /// 节点因超时或取消正在中断
+    fn node_interrupting(&self, _node: &str) {}
+
+    /// 单个节点执行结束
+    fn node_finished(&self, _result: &NodeResult) {}
+
+    /// 所有节点执行结束
+    fn exec_finished(&self) {}
+
+    /// 开始关闭连接
+    fn close_started(&self, _nodes: &[String]) {}
+
+    /// 单个节点关闭结束，失败时带有错误
+    fn node_closed(&self, _node: &str, _error: Option<&BeelogError>) {}
+
+    /// 所有连接关闭结束
+    fn close_finished(&self) {}
+}
+
+/// 不显示进度
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
+
 struct JumpServerBridge {
-    ssh_bridge: Arc<Mutex<SshBridge>>,
+    ssh_bridge: SshBridge,
     node: String,
 }
 
 impl Helper {
-    
-    /// 服务器连接
-    pub async fn connect(server_info: ServerInfo, nodes: Vec<String>) -> Self {
-        let pb = Self::default_progress_bar(nodes.len() as u64, Some("服务器连接".to_string()));
-        let pb = Arc::new(pb);
-        let mut handles = Vec::new();
-        for node in nodes {
-            let server_info_clone = server_info.clone();
-            let pb = pb.clone();
-            let handle = tokio::task::spawn_blocking(move || {
-                let mut result = SshBridge::create_bridge(server_info_clone, JUMP_SERVER_MARK);
-                if let Ok(ref mut ssh_bridge) = result {
-                    let _ = ssh_bridge.exec(node.as_str(), vec![node.clone()]);
-                }
-                pb.inc(1);
-                (node, result)
-            });
-            handles.push(handle);
+
+    /**
+     * 服务器连接
+     * 返回已连接的节点及连接失败的节点，是否继续由调用方决定
+     * 认证信息准备失败时直接返回错误；cancel 触发时尚未连接的节点返回连接中断
+     */
+    pub async fn connect(server_info: ServerInfo, node_group: NodeGroup, interaction: Arc<dyn Interaction>, cancel: CancellationToken, progress: &dyn Progress) -> Result<(Self, Vec<(String, BeelogError)>)> {
+        // 私钥密码等凭据只准备一次，所有节点共用
+        let credentials = Credentials::prepare(&server_info, interaction.as_ref())?;
+        Ok(Self::connect_with(server_info, node_group, credentials, interaction, cancel, progress).await)
+    }
+
+    /// 使用已准备好的凭据连接，如替换了 MFA 动态码来源
+    pub async fn connect_with(server_info: ServerInfo, node_group: NodeGroup, credentials: Credentials, interaction: Arc<dyn Interaction>, cancel: CancellationToken, progress: &dyn Progress) -> (Self, Vec<(String, BeelogError)>) {
+        // 会话按需创建，多个节点共用一次登录
+        let mut helper = Self {
+            jump_server_bridges: Vec::new(),
+            pool: SessionPool::new(server_info, Arc::new(credentials), interaction),
+        };
+        let failures = helper.add_nodes(node_group, cancel, progress).await;
+        (helper, failures)
+    }
+
+    /**
+     * 连接节点组中尚未连接的节点，追加到已连接的节点之后
+     * 返回连接失败的节点，cancel 触发时尚未连接的节点返回连接中断
+     */
+    pub async fn add_nodes(&mut self, node_group: NodeGroup, cancel: CancellationToken, progress: &dyn Progress) -> Vec<(String, BeelogError)> {
+        let connected = self.nodes();
+        // 上一轮的认证失败等可能已修复，本轮重新尝试登录
+        self.pool.reset_failure();
+        let init = init_commands(&self.pool.server_info, &node_group);
+        let nodes: Vec<String> = node_group.nodes.into_iter()
+            .filter(|node| !connected.contains(node))
+            .collect();
+        if nodes.is_empty() {
+            return Vec::new();
         }
-        let results = futures::future::try_join_all(handles).await.unwrap();
-        let mut jump_server_bridges = Vec::new();
-        let mut errors = HashMap::new();
+        progress.connect_started(&nodes);
+
+        let pool = &self.pool;
+        let server_info = &pool.server_info;
+        let concurrency = server_info.max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY).max(1);
+        let init = &init;
+        let cancel = &cancel;
+        let tasks = nodes.into_iter().map(|node| {
+            // 配置了期望主机的节点始终校验，其余节点开启 verify_host 时按节点名称校验
+            let expected = node_group.expected_hosts.get(&node).cloned()
+                .or_else(|| server_info.verify_host.then(|| node.clone()));
+            async move {
+                let expected = expected.as_deref();
+                let result = tokio::select! {
+                    result = Self::connect_node(pool, server_info, &node, expected, init) => result,
+                    _ = cancel.cancelled() => Err(BeelogError::Connect(tr!(ConnectInterrupted))),
+                };
+                progress.node_connected(&node, result.as_ref().err());
+                (node, result)
+            }
+        });
+        // 限制同时连接的节点数，结果保持节点顺序
+        let results: Vec<_> = futures::stream::iter(tasks).buffered(concurrency).collect().await;
+        let mut failures = Vec::new();
         for (node, result) in results {
             match result {
                 Ok(ssh_bridge) => {
                     let jump_server = JumpServerBridge {
-                        ssh_bridge: Arc::new(Mutex::new(ssh_bridge)),
+                        ssh_bridge,
                         node,
                     };
-                    jump_server_bridges.push(jump_server);
+                    self.jump_server_bridges.push(jump_server);
                 }
                 Err(e) => {
-                    errors.insert(node, e);
+                    failures.push((node, e));
                 }
             }
         }
-        pb.finish_with_message("连接完成!");
-        let mut helper = Self {
-            jump_server_bridges,
-        };
-        if !errors.is_empty() {
-            for (node, error) in errors {
-                println!("{} > 连接失败: {}", node, error);
+        progress.connect_finished();
+        failures
+    }
+
+    /// 关闭并移除指定的节点，返回关闭失败的节点
+    pub async fn remove_nodes(&mut self, nodes: &[String], progress: &dyn Progress) -> Vec<(String, BeelogError)> {
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.jump_server_bridges).into_iter()
+            .partition(|jsb| nodes.contains(&jsb.node));
+        self.jump_server_bridges = kept;
+        if removed.is_empty() {
+            return Vec::new();
+        }
+        let names: Vec<String> = removed.iter().map(|jsb| jsb.node.clone()).collect();
+        progress.close_started(&names);
+        let failures = Self::close_bridges(&removed, progress).await;
+        progress.close_finished();
+        failures
+    }
+
+    /// 已连接的节点
+    pub fn nodes(&self) -> Vec<String> {
+        self.jump_server_bridges.iter().map(|jsb| jsb.node.clone()).collect()
+    }
+
+    /**
+     * 命令执行
+     * 超时的节点发送 Ctrl-C 中断并回到提示符；cancel 触发时中断所有仍在执行的节点
+     * 每个节点结束时回调 progress，返回按节点顺序排列的结果
+     */
+    pub async fn exec(&self, command: &str, timeout_secs: u64, cancel: CancellationToken, progress: &dyn Progress) -> Vec<NodeResult> {
+        let mut results: Vec<_> = self.exec_tasks(command, timeout_secs, cancel, progress).collect().await;
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// 命令执行，按完成顺序逐个返回节点结果
+    pub fn exec_stream<'a>(&'a self, command: &'a str, timeout_secs: u64, cancel: CancellationToken, progress: &'a dyn Progress) -> impl Stream<Item = NodeResult> + 'a {
+        self.exec_tasks(command, timeout_secs, cancel, progress).map(|(_, result)| result)
+    }
+
+    /// 所有节点并发执行，结果带有节点序号；全部结束后回调 exec_finished
+    fn exec_tasks<'a>(&'a self, command: &'a str, timeout_secs: u64, cancel: CancellationToken, progress: &'a dyn Progress) -> impl Stream<Item = (usize, NodeResult)> + 'a {
+        progress.exec_started(&self.nodes());
+        let tasks: FuturesUnordered<_> = self.jump_server_bridges.iter().enumerate().map(|(index, jsb)| {
+            let cancel = cancel.clone();
+            async move {
+                let result = Self::exec_node(jsb, command, timeout_secs, &cancel, progress).await;
+                let result = NodeResult { node: jsb.node.clone(), result };
+                progress.node_finished(&result);
+                (index, result)
+            }
+        }).collect();
+        let finished = futures::stream::once(futures::future::lazy(move |_| {
+            progress.exec_finished();
+            None
+        }));
+        tasks.map(Some).chain(finished).filter_map(futures::future::ready)
+    }
+
+    /// 单个节点执行命令，收到的输出实时回调 progress
+    async fn exec_node(jsb: &JumpServerBridge, command: &str, timeout_secs: u64, cancel: &CancellationToken, progress: &dyn Progress) -> Result<NodeOutput> {
+        let prompts = vec![jsb.node.clone()];
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let exec = jsb.ssh_bridge.exec_streaming(command, prompts.clone(), timeout_secs, tx);
+        tokio::pin!(exec);
+        let mut cancelled = false;
+        let result = loop {
+            // 输出先于应答发送，优先读取输出，保证结束前已全部处理
+            tokio::select! {
+                biased;
+                Some(chunk) = rx.recv() => progress.node_output(&jsb.node, &chunk),
+                // 中断后继续读取，直到回到提示符
+                _ = cancel.cancelled(), if !cancelled => {
+                    cancelled = true;
+                    progress.node_interrupting(&jsb.node);
+                    let _ = jsb.ssh_bridge.interrupt().await;
+                }
+                result = &mut exec => break result,
             }
-            // 断开已连接的资源
-            helper.close().await;
-            exit(1);
+        };
+        let (matched, output) = result?;
+        tracing::debug!(node = jsb.node, cancelled, timed_out = matched.is_empty(), received = output.len(), "命令执行结束");
+        let status = if cancelled {
+            ExecStatus::Cancelled
+        } else if matched.is_empty() {
+            progress.node_interrupting(&jsb.node);
+            let _ = jsb.ssh_bridge.interrupt_and_wait(prompts, INTERRUPT_DRAIN_SECS).await;
+            ExecStatus::TimedOut
+        } else {
+            ExecStatus::Completed
+        };
+        Ok(NodeOutput { status, output })
+    }
+
+    /// 连接关闭，返回关闭失败的节点
+    pub async fn close(&mut self, progress: &dyn Progress) -> Vec<(String, BeelogError)> {
+        progress.close_started(&self.nodes());
+        let failures = Self::close_bridges(&self.jump_server_bridges, progress).await;
+        for session in std::mem::take(self.pool.sessions.get_mut()) {
+            let _ = session.disconnect().await;
         }
-        helper
+        self.jump_server_bridges.clear();
+
+        progress.close_finished();
+        failures
+    }
+
+    /// 关闭节点通道，返回关闭失败的节点
+    async fn close_bridges(bridges: &[JumpServerBridge], progress: &dyn Progress) -> Vec<(String, BeelogError)> {
+        let tasks = bridges.iter().map(|jsb| {
+            async move {
+                let res = jsb.ssh_bridge.close().await;
+                progress.node_closed(&jsb.node, res.as_ref().err());
+                (jsb.node.clone(), res)
+            }
+        });
+        let results = futures::future::join_all(tasks).await;
+        results.into_iter()
+            .filter_map(|(node, result)| result.err().map(|e| (node, e)))
+            .collect()
     }
 
-    /// 命令执行
-    pub async fn exec(&mut self, command: &str) {
-        let mut tasks = Vec::new();
-        for jsb in &self.jump_server_bridges {
-            let ssh_bridge = Arc::clone(&jsb.ssh_bridge);
-            let node = jsb.node.clone();
-            let command = command.to_string();
-            let task = tokio::task::spawn_blocking(move || {
-                let mut bridge = ssh_bridge.lock().unwrap();
-                (node.clone(), bridge.exec(&command, vec![node]))
-            });
-            tasks.push(task);
+    /**
+     * 登录一次，通过菜单列出可访问的资产
+     * filter 为空时输入 p 列出所有资产，否则输入 /filter 搜索；有多页时逐页读取
+     *
+     * Error 认证失败、菜单未就绪或列表超时，则返回错误
+     */
+    pub async fn discover(server_info: ServerInfo, filter: Option<&str>, interaction: Arc<dyn Interaction>) -> Result<Vec<Asset>> {
+        let credentials = Credentials::prepare(&server_info, interaction.as_ref())?;
+        let timeout_secs = server_info.timeouts.navigation;
+        let pool = SessionPool::new(server_info, Arc::new(credentials), interaction);
+        let ssh_bridge = pool.open_bridge(0).await?;
+        let result = Self::list_assets(&ssh_bridge, filter, timeout_secs).await;
+        let _ = ssh_bridge.close().await;
+        for session in pool.sessions.into_inner() {
+            let _ = session.disconnect().await;
         }
+        result
+    }
 
-        let results = futures::future::try_join_all(tasks).await.unwrap();
+    /// 输入列表命令并逐页解析，按 ID 去重；回到菜单、没有下一页或翻页后没有新资产时结束
+    async fn list_assets(ssh_bridge: &SshBridge, filter: Option<&str>, timeout_secs: u64) -> Result<Vec<Asset>> {
+        ssh_bridge.wait_ready(JUMP_SERVER_MARK, timeout_secs).await?;
+        let command = match filter {
+            Some(filter) => format!("/{}", filter),
+            None => "p".to_string(),
+        };
+        let prompts = vec![ASSET_PAGE_MARK.to_string(), JUMP_SERVER_MARK.to_string()];
+        let mut assets: Vec<Asset> = Vec::new();
+        let (mut matched, mut output) = ssh_bridge.exec_with_timeout(&command, prompts.clone(), timeout_secs).await?;
+        for _ in 0..MAX_ASSET_PAGES {
+            if matched.is_empty() {
+                return Err(BeelogError::Timeout(tr!(ListAssets)));
+            }
+            let page = asset::parse_page(&output);
+            let has_next = page.has_next();
+            let before = assets.len();
+            for asset in page.assets {
+                if !assets.iter().any(|existing| existing.id == asset.id) {
+                    assets.push(asset);
+                }
+            }
+            tracing::debug!(has_next, assets = assets.len() - before, "资产列表");
+            if matched != ASSET_PAGE_MARK || !has_next || assets.len() == before {
+                break;
+            }
+            (matched, output) = ssh_bridge.exec_with_timeout(ASSET_PAGE_NEXT, prompts.clone(), timeout_secs).await?;
+        }
+        Ok(assets)
+    }
 
-        for (node, result) in results {
+    /**
+     * 连接单个节点：开启通道、进入节点并校验，之后执行初始化命令
+     * 网络抖动、超时等可恢复错误按指数退避加随机抖动重试，重试用尽后返回最后一次的失败原因
+     */
+    async fn connect_node(pool: &SessionPool, server_info: &ServerInfo, node: &str, expected: Option<&str>, init: &[String]) -> Result<SshBridge> {
+        let mut attempt = 0;
+        loop {
+            let started = std::time::Instant::now();
+            tracing::debug!(node, attempt, "连接节点");
+            let result = match pool.open_bridge(attempt).await {
+                Ok(ssh_bridge) => {
+                    let entered = Self::enter_node(&ssh_bridge, node, expected, init, server_info.timeouts.navigation).await;
+                    if entered.is_err() {
+                        // 关闭失败的通道，避免占用会话
+                        let _ = ssh_bridge.close().await;
+                    }
+                    entered.map(|_| ssh_bridge)
+                }
+                Err(e) => Err(e),
+            };
             match result {
-                Ok(output) => {
-                    println!("======{}=======", node);
-                    println!("{}", output);
+                Err(e) if e.is_transient() && attempt < server_info.retries => {
+                    attempt += 1;
+                    let delay = backoff(attempt);
+                    tracing::warn!(node, attempt, ?delay, error = %e, "连接节点失败，稍后重试");
+                    tokio::time::sleep(delay).await;
                 }
-                Err(e) => {
-                    println!("执行命令错误: {}", e);
+                Err(e) if attempt > 0 => return Err(e.context(tr!(RetriesExhausted, attempt))),
+                result => {
+                    match &result {
+                        Ok(_) => tracing::debug!(node, elapsed = ?started.elapsed(), "节点已连接"),
+                        Err(e) => tracing::debug!(node, elapsed = ?started.elapsed(), error = %e, "连接节点失败"),
+                    }
+                    return result;
                 }
             }
         }
     }
 
-    /// 连接关闭
-    pub async fn close(&mut self) {
-        let pb = Self::default_progress_bar(self.jump_server_bridges.len() as u64, Some("关闭连接".to_string()));
-        let pb = Arc::new(pb);
-        let mut handles = Vec::new();
-        for jsb in &self.jump_server_bridges {
-            let pb = pb.clone();
-            let node = jsb.node.clone();
-            let ssh_bridge = Arc::clone(&jsb.ssh_bridge);
-            let handle = tokio::task::spawn_blocking(move || {
-                let mut bridge = ssh_bridge.lock().unwrap();
-                pb.inc(1);
-                let res = bridge.close();
-                (node.clone(), if res.is_ok() {"success".to_string()} else {res.err().unwrap().to_string()})
-            });
-            handles.push(handle);
+    /// 等待菜单就绪后进入节点，按需校验并执行初始化命令
+    async fn enter_node(ssh_bridge: &SshBridge, node: &str, expected: Option<&str>, init: &[String], timeout_secs: u64) -> Result<()> {
+        ssh_bridge.wait_ready(JUMP_SERVER_MARK, timeout_secs).await?;
+        Self::navigate(ssh_bridge, node, timeout_secs).await?;
+        if let Some(expected) = expected {
+            Self::verify_node(ssh_bridge, node, expected).await?;
         }
-        let results = futures::future::try_join_all(handles).await.unwrap();
+        Self::init_node(ssh_bridge, node, init, timeout_secs).await
+    }
 
-        for (node, result) in results {
-            if result != "success" {
-                println!("{} > 关闭失败 ", node);
+    /// 从JumpServer菜单进入节点
+    async fn navigate(ssh_bridge: &SshBridge, node: &str, timeout_secs: u64) -> Result<()> {
+        let (matched, _) = ssh_bridge.exec_with_timeout(node, vec![node.to_string()], timeout_secs).await?;
+        if matched.is_empty() {
+            return Err(BeelogError::Timeout(tr!(EnterNode, node)));
+        }
+        Ok(())
+    }
+
+    /**
+     * 校验是否进入了目标节点
+     * 执行 hostname / hostname -I，与期望的主机名或IP比较
+     * 仍停留在JumpServer菜单或结果不一致时返回错误，错误信息中包含实际输出
+     */
+    async fn verify_node(ssh_bridge: &SshBridge, node: &str, expected: &str) -> Result<()> {
+        let prompts = vec![VERIFY_END.to_string(), JUMP_SERVER_MARK.to_string()];
+        let (matched, output) = ssh_bridge.exec_with_timeout(VERIFY_COMMAND, prompts, VERIFY_TIMEOUT_SECS).await?;
+        tracing::debug!(node, expected, matched, output = %redact::escape_bytes(output.as_bytes()), "节点校验输出");
+        if matched != VERIFY_END {
+            return Err(BeelogError::Navigation(tr!(VerifyNotEntered, node, output.trim())));
+        }
+        let observed = output.split(VERIFY_BEGIN).last().unwrap_or_default()
+            .split(VERIFY_END).next().unwrap_or_default()
+            .trim();
+        let mut lines = observed.lines().map(str::trim).filter(|l| !l.is_empty());
+        let hostname = lines.next().unwrap_or_default();
+        let ips: Vec<&str> = lines.flat_map(str::split_whitespace).collect();
+        if !host_matches(expected, hostname, &ips) {
+            return Err(BeelogError::Navigation(tr!(VerifyMismatch, expected, observed.replace(['\r', '\n'], " "))));
+        }
+        // 读取剩余的命令提示符，避免混入下一次命令的输出
+        ssh_bridge.wait_for(vec![node.to_string()], 2).await?;
+        Ok(())
+    }
+
+    /**
+     * 依次执行初始化命令，每条命令回到提示符后通过 `echo $?` 检查退出码
+     * 退出码非 0 时返回错误，之后的命令不再执行
+     */
+    async fn init_node(ssh_bridge: &SshBridge, node: &str, commands: &[String], timeout_secs: u64) -> Result<()> {
+        let prompts = vec![node.to_string()];
+        for command in commands {
+            // 错误信息及日志中隐藏环境变量的值
+            let shown = redact::mask_export(command);
+            let (matched, output) = ssh_bridge.exec_with_timeout(command, prompts.clone(), timeout_secs).await?;
+            if matched.is_empty() {
+                return Err(BeelogError::Timeout(tr!(InitCommandTimeout, node, shown)));
+            }
+            let (matched, status_output) = ssh_bridge.exec_with_timeout(INIT_STATUS_COMMAND, prompts.clone(), timeout_secs).await?;
+            if matched.is_empty() {
+                return Err(BeelogError::Timeout(tr!(InitCommandTimeout, node, shown)));
+            }
+            let status = parse_init_status(&status_output);
+            tracing::debug!(node, command = %shown, ?status, "初始化命令");
+            tracing::trace!(node, output = %redact::escape_bytes(output.as_bytes()), "初始化命令输出");
+            if status != Some(0) {
+                let status = status.map_or_else(|| "?".to_string(), |status| status.to_string());
+                return Err(BeelogError::Navigation(tr!(InitCommandFailed, node, status, shown)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * 进入节点后依次执行的命令：设置环境变量、切换工作目录，之后为服务器及节点组的 init_commands
+ * 环境变量及工作目录以节点组为准
+ */
+fn init_commands(server_info: &ServerInfo, node_group: &NodeGroup) -> Vec<String> {
+    let mut env = server_info.env.clone();
+    env.extend(node_group.env.clone());
+    let mut commands: Vec<String> = env.iter()
+        .map(|(key, value)| format!("export {}={}", key, shell_quote(value)))
+        .collect();
+    if let Some(working_dir) = node_group.working_dir.as_ref().or(server_info.working_dir.as_ref()) {
+        commands.push(format!("cd {}", shell_quote(working_dir)));
+    }
+    commands.extend(server_info.init_commands.iter().chain(&node_group.init_commands).cloned());
+    commands
+}
+
+/// 按需使用单引号，`~/` 开头的路径保留在引号外以便展开
+fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        return value.to_string();
+    }
+    if let Some(rest) = value.strip_prefix("~/") {
+        return format!("~/{}", shell_quote(rest));
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// 读取退出码标记之后的数字，命令回显中的标记被引号拆分，不会被匹配
+fn parse_init_status(output: &str) -> Option<i32> {
+    let (_, status) = output.rsplit_once(INIT_STATUS_MARK)?;
+    let digits: String = status.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/**
+ * 会话池
+ * 优先在最近的会话上开启通道，只需一次MFA登录；堡垒机限制单会话通道数或会话断开时，新建会话继续开启
+ * 同一时间只有一个任务登录，其余需要新会话的任务等待其结果，已有会话可用的任务不受影响
+ * 登录失败会被记录：同一次重试的其余节点直接返回该错误，下一次重试时再登录一次，避免每个节点各自等待超时；
+ * 认证失败等不可恢复的错误在本轮中不再重试，避免重复登录触发堡垒机限制
+ */
+struct SessionPool {
+    server_info: ServerInfo,
+    credentials: Arc<Credentials>,
+    interaction: Arc<dyn Interaction>,
+    sessions: Mutex<Vec<SshSession>>,
+    /// 最近一次登录失败时的重试次数及错误，同时作为登录锁
+    failure: Mutex<Option<(u32, BeelogError)>>,
+}
+
+impl SessionPool {
+
+    fn new(server_info: ServerInfo, credentials: Arc<Credentials>, interaction: Arc<dyn Interaction>) -> Self {
+        SessionPool {
+            server_info,
+            credentials,
+            interaction,
+            sessions: Mutex::new(Vec::new()),
+            failure: Mutex::new(None),
+        }
+    }
+
+    /// 清除记录的登录失败，下次开启通道时重新登录
+    fn reset_failure(&mut self) {
+        *self.failure.get_mut() = None;
+    }
+
+    /// 开启通道，attempt 为节点的重试次数，同一次重试中登录失败的结果由各节点共用
+    async fn open_bridge(&self, attempt: u32) -> Result<SshBridge> {
+        let count = match self.open_on_last().await {
+            Ok(ssh_bridge) => return Ok(ssh_bridge),
+            Err(count) => count,
+        };
+        // 登录期间只持有 failure，其余任务仍可使用已有会话
+        let mut failure = self.failure.lock().await;
+        // 等待期间其他任务已新建会话时直接使用
+        if self.sessions.lock().await.len() > count
+            && let Ok(ssh_bridge) = self.open_on_last().await {
+            return Ok(ssh_bridge);
+        }
+        if let Some((failed, e)) = failure.as_ref()
+            && (!e.is_transient() || *failed >= attempt) {
+            return Err(e.clone());
+        }
+        let session = match SshSession::connect(self.server_info.clone(), self.credentials.clone(), self.interaction.clone()).await {
+            Ok(session) => session,
+            Err(e) => {
+                *failure = Some((attempt, e.clone()));
+                return Err(e);
             }
+        };
+        *failure = None;
+        let ssh_bridge = session.open_bridge().await;
+        self.sessions.lock().await.push(session);
+        ssh_bridge
+    }
+
+    /// 在最近的会话上开启通道，失败时返回当前的会话数
+    async fn open_on_last(&self) -> std::result::Result<SshBridge, usize> {
+        let sessions = self.sessions.lock().await;
+        if let Some(session) = sessions.last()
+            && let Ok(ssh_bridge) = session.open_bridge().await {
+            return Ok(ssh_bridge);
         }
+        Err(sessions.len())
+    }
+}
+
+/// 第 attempt 次重试前的等待时间：指数退避，并加入 50%~100% 的随机抖动
+fn backoff(attempt: u32) -> Duration {
+    let exp = BACKOFF_BASE_MILLIS.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(BACKOFF_MAX_MILLIS);
+    let jitter = RandomState::new().build_hasher().finish() % (exp / 2 + 1);
+    Duration::from_millis(exp / 2 + jitter)
+}
 
-        pb.finish_with_message("全部关闭!");
+/// 判断期望值是否与实际主机名或IP一致，主机名忽略大小写并兼容短主机名，IP 须完全一致
+fn host_matches(expected: &str, hostname: &str, ips: &[&str]) -> bool {
+    if ips.contains(&expected) {
+        return true;
     }
-    
-    /// 默认进度条
-    fn default_progress_bar(len: u64, prefix: Option<String>) -> ProgressBar {
-        let pb = ProgressBar::new(len);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{prefix} {bar:40.cyan/blue} {pos:>5}/{len:5} {msg}").unwrap()
-            .progress_chars("=> "));
-        if let Some(prefix) = prefix {
-            pb.set_prefix(prefix);
+    if hostname.is_empty() || expected.parse::<IpAddr>().is_ok() || hostname.parse::<IpAddr>().is_ok() {
+        return false;
+    }
+    if hostname.eq_ignore_ascii_case(expected) {
+        return true;
+    }
+    // 只有一方为短主机名时比较第一段，两个完整域名须完全一致
+    match (hostname.split_once('.'), expected.split_once('.')) {
+        (Some((short, _)), None) => short.eq_ignore_ascii_case(expected),
+        (None, Some((short, _))) => short.eq_ignore_ascii_case(hostname),
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interaction::NonInteractive;
+
+    #[test]
+    fn test_backoff() {
+        for attempt in 1..10 {
+            let exp = (BACKOFF_BASE_MILLIS << (attempt - 1)).min(BACKOFF_MAX_MILLIS);
+            let delay = backoff(attempt).as_millis() as u64;
+            assert!(delay >= exp / 2 && delay <= exp, "attempt {}: {}", attempt, delay);
         }
-        pb
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_shared_login_failure() {
+        // 接受连接后立即关闭，握手失败为可重试的错误
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let logins = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = logins.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                drop(stream);
+            }
+        });
+        let mut server_info = ServerInfo::new("127.0.0.1", port, "ops");
+        server_info.retries = 2;
+        let nodes: Vec<String> = (1..=4).map(|i| format!("node{}", i)).collect();
+        let node_group = NodeGroup { nodes: nodes.clone(), ..NodeGroup::default() };
+        let (_, failures) = Helper::connect_with(server_info, node_group, Credentials::default(), Arc::new(NonInteractive), CancellationToken::new(), &NoProgress).await;
+        assert_eq!(failures.len(), nodes.len());
+        assert!(failures.iter().all(|(_, e)| e.is_transient()));
+        // 每次重试只登录一次
+        assert_eq!(logins.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_init_commands() {
+        let mut server_info = ServerInfo::new("jump", 2222, "ops");
+        server_info.working_dir = Some("/data".to_string());
+        server_info.env.insert("LANG".to_string(), "C".to_string());
+        server_info.init_commands.push("unset HISTFILE".to_string());
+        let mut node_group = NodeGroup { working_dir: Some("~/app logs".to_string()), ..NodeGroup::default() };
+        node_group.env.insert("GREETING".to_string(), "it's ok".to_string());
+        node_group.init_commands.push("sudo -iu app".to_string());
+        assert_eq!(init_commands(&server_info, &node_group), [
+            "export GREETING='it'\\''s ok'",
+            "export LANG=C",
+            "cd ~/'app logs'",
+            "unset HISTFILE",
+            "sudo -iu app",
+        ]);
+        assert!(init_commands(&ServerInfo::new("jump", 2222, "ops"), &NodeGroup::default()).is_empty());
+
+        assert_eq!(parse_init_status("echo __BEELOG_''INIT_STATUS__$?\r\n__BEELOG_INIT_STATUS__127\r\n[root@app-01 ~]# "), Some(127));
+        assert_eq!(parse_init_status("echo __BEELOG_''INIT_STATUS__$?\r\n"), None);
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("app-01", "app-01", &[]));
+        assert!(host_matches("APP-01", "app-01.prod.local", &[]));
+        assert!(host_matches("10.0.1.12", "app-01", &["10.0.1.12", "172.17.0.1"]));
+        assert!(!host_matches("app-02", "app-01", &["10.0.1.12"]));
+        assert!(!host_matches("app-01", "", &[]));
+        assert!(host_matches("app-01.prod.local", "APP-01", &[]));
+        assert!(!host_matches("web01.prod", "web01.staging", &[]));
+        assert!(!host_matches("10.0.0.5", "10", &["10.0.0.6"]));
+        assert!(!host_matches("10.0.0.5", "10.0.0.6", &[]));
+        assert!(!host_matches("10", "10.0.0.5", &[]));
+    }
+}
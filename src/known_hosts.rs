@@ -0,0 +1,69 @@
+use std::fs;
+use std::sync::Mutex;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use ssh2::{CheckResult, HashType, KnownHostFileKind, Session};
+use crate::config::{self, HostKeyPolicy};
+use crate::error::{BeelogError, Result};
+use crate::interaction::Interaction;
+use crate::tr;
+
+
+/// 多个连接同时校验时串行处理，保证首次连接只提示一次且文件写入不冲突
+static VERIFY_LOCK: Mutex<()> = Mutex::new(());
+
+/**
+ * 校验服务器公钥
+ * 使用 beelog 自己维护的 known_hosts 文件（配置目录下），校验策略：
+ *      strict      未知或不匹配的公钥都拒绝连接
+ *      accept-new  未知公钥经 interaction 确认指纹后写入 known_hosts；未确认或不匹配时拒绝
+ *      accept-new-unattended  未知公钥不经确认直接写入 known_hosts；不匹配时拒绝
+ *      off         不校验
+ */
+pub fn verify_host_key(sess: &Session, host: &str, port: u16, policy: HostKeyPolicy, interaction: &dyn Interaction) -> Result<()> {
+    if policy == HostKeyPolicy::Off {
+        return Ok(());
+    }
+    let (key, key_type) = sess.host_key().ok_or_else(|| BeelogError::Auth(tr!(HostKeyUnavailable)))?;
+    let fingerprint = fingerprint(sess).ok_or_else(|| BeelogError::Auth(tr!(HostKeyNoFingerprint)))?;
+    tracing::debug!(host, port, ?key_type, fingerprint, ?policy, "校验服务器公钥");
+
+    let _guard = VERIFY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = config::get_known_hosts_path()?;
+    let mut known_hosts = sess.known_hosts().map_err(|e| BeelogError::Auth(tr!(HostKeyCheckError, e)))?;
+    if path.exists() {
+        known_hosts.read_file(&path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| BeelogError::Config(tr!(ReadFileFailed, path.display(), e)))?;
+    }
+    let check = known_hosts.check_port(host, port, key);
+    tracing::debug!(known_hosts = %path.display(), ?check, "known_hosts 校验结果");
+    match check {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(BeelogError::Auth(tr!(HostKeyMismatch, host, port, path.display(), fingerprint))),
+        CheckResult::Failure => Err(BeelogError::Auth(tr!(HostKeyCheckFailed))),
+        CheckResult::NotFound => {
+            if policy == HostKeyPolicy::Strict {
+                return Err(BeelogError::Auth(tr!(HostKeyNotRecorded, host, port, path.display(), fingerprint)));
+            }
+            if policy == HostKeyPolicy::AcceptNewUnattended {
+                tracing::warn!(host, port, fingerprint, "未经确认记录服务器公钥");
+            } else if !interaction.confirm_host_key(host, port, &format!("{:?}", key_type), &fingerprint) {
+                return Err(BeelogError::Auth(tr!(HostKeyNotTrusted, host, port, fingerprint)));
+            }
+            let entry = if port == 22 { host.to_string() } else { format!("[{}]:{}", host, port) };
+            let write_error = |e: &dyn std::fmt::Display| BeelogError::Config(tr!(WriteFileFailed, path.display(), e));
+            known_hosts.add(&entry, key, "added by beelog", key_type.into()).map_err(|e| write_error(&e))?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| write_error(&e))?;
+            }
+            known_hosts.write_file(&path, KnownHostFileKind::OpenSSH).map_err(|e| write_error(&e))?;
+            Ok(())
+        }
+    }
+}
+
+/// 公钥 SHA256 指纹，格式与 OpenSSH 一致
+fn fingerprint(sess: &Session) -> Option<String> {
+    sess.host_key_hash(HashType::Sha256)
+        .map(|hash| format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)))
+}
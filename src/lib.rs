@@ -1,5 +1,22 @@
 pub mod config;
+pub mod config_check;
+pub mod config_edit;
+pub mod error;
 pub mod args;
 pub mod ssh_bridge;
 pub mod mfa;
-pub mod jump_server_helper;
\ No newline at end of file
+pub mod node_pattern;
+pub mod selector;
+pub mod inventory;
+pub mod nodes_cmd;
+pub mod asset;
+pub mod auth;
+pub mod known_hosts;
+pub mod interaction;
+pub mod jump_server_helper;
+pub mod output;
+pub mod client;
+pub mod redact;
+pub mod i18n;
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,36 @@
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+use beelog::tr;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+/**
+ * 初始化调试日志
+ * 未指定 -v 及 --log-file 时不记录；-v 为 debug，-vv 为 trace（含收到的原始输出）
+ * 只记录 beelog 自身的日志，密码等敏感内容在记录前已替换
+ */
+pub fn init(verbose: u8, log_file: Option<&Path>) -> Result<(), String> {
+    let level = match (verbose, log_file) {
+        (0, None) => return Ok(()),
+        (0 | 1, _) => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let filter = Targets::new().with_target(env!("CARGO_CRATE_NAME"), level);
+    let layer = tracing_subscriber::fmt::layer().with_target(false);
+    match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|e| tr!(LogFileOpenFailed, path.display(), e))?;
+            let layer = layer.with_ansi(false).with_writer(Mutex::new(file));
+            tracing_subscriber::registry().with(layer.with_filter(filter)).init();
+        }
+        None => {
+            let layer = layer.with_ansi(std::io::stderr().is_terminal()).with_writer(std::io::stderr);
+            tracing_subscriber::registry().with(layer.with_filter(filter)).init();
+        }
+    }
+    Ok(())
+}
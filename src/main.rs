@@ -1,44 +1,171 @@
-use beelog::args;
-use beelog::config;
-use beelog::jump_server_helper;
+use beelog::args::{self, Command};
+use beelog::config::{self, NodeGroup, ReloadDiff, SelectedServer, ServerInfo, Timeouts};
+use beelog::config_check::{self, Severity};
+use beelog::i18n;
+use beelog::jump_server_helper::Helper;
+use beelog::node_pattern;
+use beelog::tr;
 use reedline::Signal;
 use std::process::exit;
+use std::sync::Arc;
+use terminal::{OutputOrder, TerminalInteraction, TerminalProgress};
+use tokio_util::sync::CancellationToken;
 
 mod cli_line;
+mod config_cmd;
+mod discover_cmd;
+mod group_cmd;
+mod import_cmd;
+mod logging;
+mod terminal;
 
 const QUIT : &str = "quit";
 
 #[tokio::main]
 async fn main() {
+    i18n::init_from_env();
+    // 帮助信息及参数错误也按配置中的 language 输出
+    if let Ok(path) = config::get_config_file_path(args::config_arg().as_deref()) {
+        config_cmd::apply_language(&path);
+    }
     let args = args::init();
+    if let Err(e) = logging::init(args.verbose, args.log_file.as_deref()) {
+        println!("{}", e);
+        exit(1);
+    }
+    match &args.command {
+        Some(Command::Config(command)) => exit(config_cmd::run(command, args.config.as_deref())),
+        Some(Command::Import { sources, dry_run }) => exit(import_cmd::run(sources, *dry_run, args.config.as_deref())),
+        Some(Command::Group(command)) => exit(group_cmd::run(command, args.config.as_deref())),
+        Some(Command::Discover { server, filter, save }) => {
+            exit(discover_cmd::run(server.as_deref(), filter.as_deref(), save.as_deref(), args.config.as_deref()).await)
+        }
+        None => {}
+    }
     // 读取配置
-    let server_res = config::read_server_config(&args);
+    let server_res = config::read_server_config(&args, &TerminalInteraction);
     if let Err(err) = server_res {
-        println!("读取配置异常: {}", err);
+        println!("{}", tr!(ReadConfigFailed, err));
+        exit(1);
+    }
+    // 当前的服务器及节点，:select / :reload 时更新
+    let mut selected = server_res.unwrap();
+    print_nodes_cmd_errors(&selected);
+    let initial_selection = selected.selection.clone();
+    let mut command_timeout = selected.server_info.timeouts.command;
+    let mut progress = TerminalProgress::new(selected.server_info.output_format);
+    if args.first_come {
+        progress.order = OutputOrder::FirstCome;
+    }
+    let cancel = CancellationToken::new();
+    let connect = Helper::connect(selected.server_info.clone(), selected.node_group.clone(), Arc::new(TerminalInteraction), cancel.clone(), &progress);
+    let connected = cancel_on_ctrl_c(connect, &cancel, &tr!(ConnectInterrupting)).await;
+    let (mut helper, failures) = match connected {
+        Ok(connected) => connected,
+        Err(e) => {
+            println!("{}", tr!(PrepareCredentialsFailed, e));
+            exit(1);
+        }
+    };
+    if !failures.is_empty() {
+        for (node, error) in &failures {
+            terminal::print_node_error(node, &tr!(ActionConnect), error);
+        }
+        // 断开已连接的资源
+        close(&mut helper, &progress).await;
         exit(1);
     }
-    let (server_info, node_group) = server_res.unwrap();
-    let nodes = node_group.nodes;
-    let mut helper = jump_server_helper::Helper::connect(server_info, nodes).await;
 
-    let cli = cli_line::CliLine::new(&node_group.group);
+    let cli = cli_line::CliLine::new(&selected.node_group.group);
     let mut line_editor = cli.line_editor;
-    let prompt = cli.prompt;
+    let mut prompt = cli.prompt;
 
     loop {
         let sig = line_editor.read_line(&prompt);
         match sig {
             Ok(Signal::Success(line)) => {
-                let command = line.trim();
-                if "".eq(command) {
+                let mut command = line.trim().to_string();
+                let mut timeout = command_timeout;
+                if let Some(meta) = cli_line::parse_meta_command(&command) {
+                    match meta {
+                        Ok(cli_line::MetaCommand::Timeout) => {
+                            println!("{}", tr!(CurrentTimeout, command_timeout));
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::SetTimeout(secs)) => {
+                            command_timeout = secs;
+                            println!("{}", tr!(TimeoutSet, secs));
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::FirstCome) => {
+                            progress.order = match progress.order {
+                                OutputOrder::Ordered => {
+                                    println!("{}", tr!(OrderFirstCome));
+                                    OutputOrder::FirstCome
+                                }
+                                OutputOrder::FirstCome => {
+                                    println!("{}", tr!(OrderOrdered));
+                                    OutputOrder::Ordered
+                                }
+                            };
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::Format(format)) => {
+                            match format {
+                                Some(format) => {
+                                    progress.format = format;
+                                    println!("{}", tr!(FormatSet, format!("{:?}", format).to_lowercase()));
+                                }
+                                None => println!("{}", tr!(CurrentFormat, format!("{:?}", progress.format).to_lowercase())),
+                            }
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::Select(expr)) => {
+                            let selection = match expr {
+                                Some(expr) => config::NodeSelection { group: None, select: Some(expr) },
+                                None => initial_selection.clone(),
+                            };
+                            match selected.inventory.resolve(&selection) {
+                                Ok(node_group) => {
+                                    prompt.set_left_prompt(node_group.group.clone());
+                                    selected.node_group = node_group.clone();
+                                    selected.selection = selection;
+                                    select_nodes(&mut helper, &progress, node_group).await;
+                                }
+                                Err(e) => println!("⚠️ {}", e),
+                            }
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::SaveGroup { name, force }) => {
+                            save_group(&args, &helper, &selected, name, force);
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::Reload) => {
+                            if let Some(reloaded) = reload(&args, &selected, &mut helper, &mut progress, &mut command_timeout).await {
+                                prompt.set_left_prompt(reloaded.node_group.group.clone());
+                                selected = reloaded;
+                            }
+                            continue;
+                        }
+                        Ok(cli_line::MetaCommand::Exec { timeout: secs, command: cmd }) => {
+                            timeout = secs;
+                            command = cmd;
+                        }
+                        Err(e) => {
+                            println!("⚠️ {}", e);
+                            continue;
+                        }
+                    }
+                }
+                if command.is_empty() {
                     continue;
-                } else if QUIT.eq(command) {
+                } else if QUIT.eq(&command) {
                     break;
-                } else if cli_line::is_command_blocked(command) {
-                    println!("⚠️ 命令 `{}` 被禁止执行：可能导致会话阻塞", command);
+                } else if cli_line::is_command_blocked(&command) {
+                    println!("{}", tr!(CommandBlocked, command));
                     continue;
                 }
-                helper.exec(command).await;
+                exec_command(&helper, &progress, &command, timeout).await;
             }
             Ok(Signal::CtrlC) => {
                 let _ = line_editor.clear_scrollback();
@@ -46,10 +173,161 @@ async fn main() {
             Ok(Signal::CtrlD) => {
                 break;
             }
-            x => {
-                println!("Event: {:?}", x);
+            other => {
+                tracing::debug!(?other, "忽略的输入事件");
             }
         }
     }
-    helper.close().await;
-}
\ No newline at end of file
+    close(&mut helper, &progress).await;
+}
+
+/**
+ * 等待 future 完成，期间 Ctrl-C 时输出 message 并触发 cancel，继续等待 future 结束
+ * 首次监听 Ctrl-C 后 SIGINT 不再结束进程，耗时的操作都需经此处理才能中断
+ */
+async fn cancel_on_ctrl_c<F: Future>(future: F, cancel: &CancellationToken, message: &str) -> F::Output {
+    tokio::pin!(future);
+    tokio::select! {
+        output = &mut future => output,
+        _ = tokio::signal::ctrl_c() => {
+            println!("{}", message);
+            cancel.cancel();
+            future.await
+        }
+    }
+}
+
+/// 执行命令并输出结果，执行期间 Ctrl-C 中断所有仍在执行的节点
+async fn exec_command(helper: &Helper, progress: &TerminalProgress, command: &str, timeout_secs: u64) {
+    let cancel = CancellationToken::new();
+    let exec = helper.exec(command, timeout_secs, cancel.clone(), progress);
+    let results = cancel_on_ctrl_c(exec, &cancel, &tr!(Interrupting)).await;
+    progress.print_results(&results, timeout_secs);
+}
+
+/// 切换为节点组中的节点：先连接新增的节点，再关闭不在节点组中的节点，连接期间 Ctrl-C 中断尚未连接的节点
+async fn select_nodes(helper: &mut Helper, progress: &TerminalProgress, node_group: NodeGroup) {
+    let removed: Vec<String> = helper.nodes().into_iter()
+        .filter(|node| !node_group.nodes.contains(node))
+        .collect();
+    let cancel = CancellationToken::new();
+    let added = helper.add_nodes(node_group, cancel.clone(), progress);
+    for (node, error) in cancel_on_ctrl_c(added, &cancel, &tr!(ConnectInterrupting)).await {
+        terminal::print_node_error(&node, &tr!(ActionConnect), &error);
+    }
+    for (node, error) in helper.remove_nodes(&removed, progress).await {
+        terminal::print_node_error(&node, &tr!(ActionClose), &error);
+    }
+    let nodes = helper.nodes();
+    println!("{}", tr!(NodesSelected, nodes.len(), nodes.join(", ")));
+}
+
+/// 未选择的节点组 nodes_cmd 失败时只提示，这些节点组只包含配置的节点
+fn print_nodes_cmd_errors(selected: &SelectedServer) {
+    for (_, error) in selected.inventory.nodes_cmd_errors() {
+        println!("⚠️ {}", error);
+    }
+}
+
+/// 将已连接的节点保存为节点组，只适用于当前服务器
+fn save_group(args: &args::Args, helper: &Helper, selected: &SelectedServer, name: String, force: bool) {
+    let nodes = helper.nodes();
+    let expected_hosts = selected.node_group.expected_hosts.iter()
+        .filter(|(node, _)| nodes.contains(node))
+        .map(|(node, host)| (node.clone(), host.clone()))
+        .collect();
+    let group = NodeGroup {
+        group: name,
+        // 节点名称原样保存，其中的 `[` 不作为范围
+        nodes: nodes.iter().map(|node| node_pattern::escape(node)).collect(),
+        expected_hosts,
+        servers: vec![selected.server_info.name.clone()],
+        working_dir: selected.node_group.working_dir.clone(),
+        env: selected.node_group.env.clone(),
+        init_commands: selected.node_group.init_commands.clone(),
+        ..NodeGroup::default()
+    };
+    let saved = config::get_config_file_path(args.config.as_deref())
+        .map_err(|e| e.to_string())
+        .and_then(|path| group_cmd::save(&path, &group, force));
+    match saved {
+        Ok(message) => println!("{}", message),
+        Err(e) => println!("⚠️ {}", e),
+    }
+}
+
+/**
+ * 重新读取配置并应用：连接新增的节点、断开移除的节点，输出格式及命令超时有变化时直接应用
+ * 服务器的连接参数有变化时只提示，已连接的节点保持原连接
+ * return 应用后的服务器及节点，配置有错误或无法按原方式选择节点时不做修改，返回 None
+ */
+async fn reload(args: &args::Args, selected: &SelectedServer, helper: &mut Helper, progress: &mut TerminalProgress, command_timeout: &mut u64) -> Option<SelectedServer> {
+    let path = match config::get_config_file_path(args.config.as_deref()) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("⚠️ {}", tr!(ReloadFailed, e));
+            return None;
+        }
+    };
+    let errors: Vec<_> = config_check::check_config(&path).into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .collect();
+    if !errors.is_empty() {
+        for diagnostic in &errors {
+            println!("{}: {}", diagnostic.severity, diagnostic);
+        }
+        println!("⚠️ {}", tr!(ReloadInvalid));
+        return None;
+    }
+    let mut reloaded = match config::reload_server_config(args, &selected.server_info.name, &selected.selection) {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            println!("⚠️ {}", tr!(ReloadFailed, e));
+            return None;
+        }
+    };
+
+    print_nodes_cmd_errors(&reloaded);
+    let diff = ReloadDiff::new(selected, &helper.nodes(), &reloaded);
+    let new_info = reloaded.server_info;
+    // 已连接的节点仍使用原连接参数，记录原参数以便再次重新加载时继续提示
+    reloaded.server_info = ServerInfo {
+        output_format: new_info.output_format,
+        timeouts: Timeouts { command: new_info.timeouts.command, ..selected.server_info.timeouts.clone() },
+        ..selected.server_info.clone()
+    };
+    let mut changed = !diff.is_empty();
+    if new_info.timeouts.command != selected.server_info.timeouts.command {
+        *command_timeout = new_info.timeouts.command;
+        println!("{}", tr!(TimeoutSet, new_info.timeouts.command));
+        changed = true;
+    }
+    if new_info.output_format != selected.server_info.output_format {
+        progress.format = new_info.output_format;
+        println!("{}", tr!(FormatSet, format!("{:?}", new_info.output_format).to_lowercase()));
+        changed = true;
+    }
+    if !diff.added_groups.is_empty() {
+        println!("{}", tr!(ReloadGroupsAdded, diff.added_groups.join(", ")));
+    }
+    if !diff.removed_groups.is_empty() {
+        println!("{}", tr!(ReloadGroupsRemoved, diff.removed_groups.join(", ")));
+    }
+    if diff.server_changed {
+        println!("{}", tr!(ReloadServerChanged, selected.server_info.name));
+    }
+    if !diff.added_nodes.is_empty() || !diff.removed_nodes.is_empty() {
+        select_nodes(helper, progress, reloaded.node_group.clone()).await;
+    }
+    if !changed {
+        println!("{}", tr!(ReloadUnchanged));
+    }
+    Some(reloaded)
+}
+
+/// 关闭连接
+async fn close(helper: &mut Helper, progress: &TerminalProgress) {
+    for (node, error) in helper.close(progress).await {
+        terminal::print_node_error(&node, &tr!(ActionClose), &error);
+    }
+}
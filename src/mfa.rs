@@ -3,16 +3,51 @@ use base32::decode;
 use hmac::{Hmac, Mac};
 use sha1::Sha1;
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::{BeelogError, Result};
+use crate::tr;
 
 type HmacSha1 = Hmac<Sha1>;
 
-pub fn get_google_code(secret: &str) -> String {
-    format!("{:06}", get_totp_token(secret, 3))
+/**
+ * MFA 动态码来源
+ * 默认使用配置中的 secret_code 生成 TOTP，嵌入方可替换为硬件令牌、密码管理器等
+ */
+pub trait MfaProvider: Send + Sync {
+
+    /// 返回当前的动态码，认证遇到 OTP 提示时调用
+    fn code(&self) -> Result<String>;
+}
+
+/// 基于 base32 密钥生成 TOTP 动态码（Google Authenticator）
+pub struct TotpProvider {
+    secret: String,
+}
+
+impl TotpProvider {
+
+    /// 创建时校验密钥，避免认证时才发现格式错误
+    pub fn new(secret: impl Into<String>) -> Result<Self> {
+        let secret = secret.into();
+        get_google_code(&secret)?;
+        Ok(TotpProvider { secret })
+    }
 }
 
-fn get_hotp_token(secret: &str, intervals_no: u64) -> u32 {
+impl MfaProvider for TotpProvider {
+    fn code(&self) -> Result<String> {
+        get_google_code(&self.secret)
+    }
+}
+
+pub fn get_google_code(secret: &str) -> Result<String> {
+    let token = get_totp_token(secret, 3)?;
+    Ok(format!("{:06}", token))
+}
+
+fn get_hotp_token(secret: &str, intervals_no: u64) -> Result<u32> {
     // Decode base32 secret
-    let key = decode(Rfc4648 { padding: false }, secret).expect("Invalid base32 secret");
+    let key = decode(Rfc4648 { padding: false }, secret)
+        .ok_or_else(|| BeelogError::Decode(tr!(InvalidSecretCode)))?;
 
     // Convert interval to 8-byte array (big-endian)
     let msg = intervals_no.to_be_bytes();
@@ -25,12 +60,10 @@ fn get_hotp_token(secret: &str, intervals_no: u64) -> u32 {
     // Dynamic truncation
     let offset = (hmac_result[19] & 0x0f) as usize;
     let four_bytes = &hmac_result[offset..offset + 4];
-    let code = ((u32::from_be_bytes(four_bytes.try_into().unwrap())) & 0x7fffffff) % 1_000_000;
-
-    code
+    Ok(((u32::from_be_bytes(four_bytes.try_into().unwrap())) & 0x7fffffff) % 1_000_000)
 }
 
-fn get_totp_token(secret: &str, bias: i64) -> u32 {
+fn get_totp_token(secret: &str, bias: i64) -> Result<u32> {
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
     let time_step = (now + bias) / 30;
     get_hotp_token(secret, time_step as u64)
@@ -44,9 +77,12 @@ mod tests {
 
     #[test]
     fn test_get_hotp_token() {
-        let secret = "xxxx"; // 替换为 Google MFA 的 base32 秘钥
-        let code = get_google_code(secret);
-        println!("{}", code);
+        let secret = "JBSWY3DPEHPK3PXP"; // 替换为 Google MFA 的 base32 秘钥
+        let code = get_google_code(secret).unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(get_google_code("xxxx").is_err());
+        assert!(TotpProvider::new("xxxx").is_err());
+        assert_eq!(TotpProvider::new(secret).unwrap().code().unwrap().len(), 6);
     }
 
 }
\ No newline at end of file
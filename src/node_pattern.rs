@@ -0,0 +1,134 @@
+//! 节点范围展开及自然排序
+
+use std::cmp::Ordering;
+use crate::error::{BeelogError, Result};
+use crate::tr;
+
+/// 单个范围最多展开的节点数，避免误写的范围生成大量节点
+const MAX_EXPANDED: usize = 10000;
+
+/**
+ * 展开节点范围
+ *      app-[01-20]     app-01 ... app-20，起始值以 0 开头时按其位数补零
+ *      10.0.1.[10-30]  10.0.1.10 ... 10.0.1.30
+ *      r[1-2]-n[1-3]   多个范围按笛卡尔积展开
+ *      odd\[1]        `\` 之后的字符原样保留，用于名称中的 `[`
+ * 不含范围时原样返回
+ */
+pub fn expand(pattern: &str) -> Result<Vec<String>> {
+    let mut prefix = String::new();
+    let mut chars = pattern.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '\\' => prefix.push(chars.next().map_or('\\', |(_, c)| c)),
+            '[' => return expand_range(pattern, prefix, pos),
+            c => prefix.push(c),
+        }
+    }
+    Ok(vec![prefix])
+}
+
+/// 展开 open 位置开始的范围，prefix 为范围之前已去除转义的内容
+fn expand_range(pattern: &str, prefix: String, open: usize) -> Result<Vec<String>> {
+    let invalid = || BeelogError::Config(tr!(NodeRangeInvalid, pattern));
+    let close = pattern[open..].find(']').map(|pos| open + pos).ok_or_else(invalid)?;
+    let (start, end) = pattern[open + 1..close].split_once('-').ok_or_else(invalid)?;
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_number(start) || !is_number(end) {
+        return Err(invalid());
+    }
+    let (first, last) = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(first), Ok(last)) if first <= last => (first, last),
+        _ => return Err(invalid()),
+    };
+    let width = if start.len() > 1 && start.starts_with('0') { start.len() } else { 0 };
+    let rest = expand(&pattern[close + 1..])?;
+    let count = (last - first + 1).saturating_mul(rest.len() as u64);
+    if count > MAX_EXPANDED as u64 {
+        return Err(BeelogError::Config(tr!(NodeRangeTooLarge, pattern, MAX_EXPANDED)));
+    }
+
+    let mut nodes = Vec::with_capacity(count as usize);
+    for value in first..=last {
+        for suffix in &rest {
+            nodes.push(format!("{}{:0width$}{}", prefix, value, suffix, width = width));
+        }
+    }
+    Ok(nodes)
+}
+
+/// 转义节点名称中的 `[` 及 `\`，保存为配置时 expand 原样返回该名称
+pub fn escape(node: &str) -> String {
+    let mut escaped = String::with_capacity(node.len());
+    for c in node.chars() {
+        if c == '[' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 自然排序：连续数字按数值比较，如 app-2 < app-10、10.0.1.9 < 10.0.1.10
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_digits, x_rest) = split_digits(a);
+                let (y_digits, y_rest) = split_digits(b);
+                let (x_value, y_value) = (x_digits.trim_start_matches('0'), y_digits.trim_start_matches('0'));
+                let ordering = x_value.len().cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                (a, b) = (x_rest, y_rest);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+            }
+        }
+    }
+}
+
+/// 拆分开头的连续数字
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("app-01").unwrap(), ["app-01"]);
+        assert_eq!(expand("app-[08-11]").unwrap(), ["app-08", "app-09", "app-10", "app-11"]);
+        assert_eq!(expand("10.0.1.[9-11]").unwrap(), ["10.0.1.9", "10.0.1.10", "10.0.1.11"]);
+        assert_eq!(expand("r[1-2]-n[1-2]").unwrap(), ["r1-n1", "r1-n2", "r2-n1", "r2-n2"]);
+        for invalid in ["app-[20-01]", "app-[01-]", "app-[a-c]", "app-[01-20", "app-[0-99999]"] {
+            assert!(matches!(expand(invalid), Err(BeelogError::Config(_))), "{}", invalid);
+        }
+        assert_eq!(expand(r"odd\[1]-[1-2]").unwrap(), ["odd[1]-1", "odd[1]-2"]);
+        for node in ["odd[1]", r"a\b", "web,01", "app-[01-02]"] {
+            assert_eq!(expand(&escape(node)).unwrap(), [node]);
+        }
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut nodes = vec!["app-10", "app-9", "db-1", "app-09", "10.0.1.10", "10.0.1.9", "app"];
+        nodes.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(nodes, ["10.0.1.9", "10.0.1.10", "app", "app-9", "app-09", "app-10", "db-1"]);
+    }
+}
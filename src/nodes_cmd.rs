@@ -0,0 +1,234 @@
+//! 通过本地命令获取节点组的节点
+//!
+//! 命令的标准输出为节点列表，支持：
+//! ```text
+//! app-01                                  每行一个节点，忽略空行及 # 开头的行
+//! ["app-01", "app-02"]                    JSON 字符串数组
+//! [{"name": "app-01", "host": "10.0.0.1"}] JSON 对象数组，host 可选，作为节点校验的期望主机
+//! ```
+//! 输出按命令缓存，有效期内不再执行；命令失败或超时时使用过期的缓存，缓存有效期为 0 时不使用缓存。
+//! 输出的节点名称原样使用，不展开范围。
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use crate::auth;
+use crate::error::{BeelogError, Result};
+use crate::tr;
+
+/// 默认超时时间（秒）
+pub const DEFAULT_TIMEOUT: u64 = 10;
+/// 默认缓存有效期（秒）
+pub const DEFAULT_TTL: u64 = 300;
+/// 等待命令结束的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// 错误信息中保留的标准错误长度
+const MAX_STDERR: usize = 200;
+
+/// 命令输出的节点
+#[derive(Debug, Default, PartialEq)]
+pub struct DynamicNodes {
+    pub nodes: Vec<String>,
+    /// 节点期望的主机名或IP
+    pub expected_hosts: HashMap<String, String>,
+}
+
+/// JSON 输出中的节点
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonNode {
+    Name(String),
+    Node {
+        name: String,
+        #[serde(default)]
+        host: Option<String>,
+    },
+}
+
+/**
+ * 执行命令获取节点，cache_dir 下存在未过期的缓存时直接使用
+ * ttl 为 0 时不读写缓存；否则命令失败或超时且存在缓存时使用该过期的缓存
+ *
+ * Error 命令执行失败、超时、退出码非 0 或输出无法解析且没有缓存，则返回错误
+ */
+pub fn load(group: &str, command: &str, timeout: Duration, ttl: Duration, cache_dir: &Path) -> Result<DynamicNodes> {
+    let cache = cache_dir.join(cache_name(command));
+    if !ttl.is_zero() && let Some(output) = read_cache(&cache, Some(ttl)) {
+        tracing::debug!(group, cache = %cache.display(), "使用节点命令缓存");
+        return parse(group, &output);
+    }
+    let started = Instant::now();
+    let result = run(group, command, timeout).and_then(|output| parse(group, &output).map(|nodes| (output, nodes)));
+    match result {
+        Ok((output, nodes)) => {
+            tracing::debug!(group, nodes = nodes.nodes.len(), elapsed = ?started.elapsed(), "节点命令执行结束");
+            if !ttl.is_zero() {
+                let written = fs::create_dir_all(cache_dir).and_then(|_| fs::write(&cache, &output));
+                if let Err(e) = written {
+                    tracing::debug!(group, cache = %cache.display(), error = %e, "写入节点命令缓存失败");
+                }
+            }
+            Ok(nodes)
+        }
+        Err(e) => match read_cache(&cache, None).filter(|_| !ttl.is_zero()) {
+            Some(output) => {
+                tracing::warn!(group, error = %e, "节点命令失败，使用过期的缓存");
+                parse(group, &output)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// 缓存文件名为命令的 SHA-1
+fn cache_name(command: &str) -> String {
+    Sha1::digest(command.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 读取缓存，指定 ttl 时忽略过期的缓存
+fn read_cache(path: &Path, ttl: Option<Duration>) -> Option<String> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+    let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+    if ttl.is_some_and(|ttl| age > ttl) {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+/// 执行命令并读取标准输出，超时后结束该命令及其启动的进程
+fn run(group: &str, command: &str, timeout: Duration) -> Result<String> {
+    let failed = |e: &dyn std::fmt::Display| BeelogError::Config(tr!(NodesCmdFailed, group, e));
+    let mut command = auth::shell_command(command);
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    // 在单独的进程组中执行，超时后结束整个进程组，而不只是 shell
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn().map_err(|e| failed(&e))?;
+    // 在单独的线程中读取，避免输出较多时填满管道导致命令阻塞
+    let mut stdout = child.stdout.take().expect("stdout piped");
+    let mut stderr = child.stderr.take().expect("stderr piped");
+    let stdout = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let stderr = thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output);
+        output
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| failed(&e))? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                kill(&mut child);
+                let _ = child.wait();
+                return Err(BeelogError::Config(tr!(NodesCmdTimeout, group, timeout.as_secs())));
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    };
+    let stdout = stdout.join().expect("stdout reader").map_err(|e| failed(&e))?;
+    let stderr = stderr.join().expect("stderr reader");
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        let stderr: String = stderr.trim().chars().take(MAX_STDERR).collect();
+        return Err(failed(&format!("{} {}", status, stderr).trim_end()));
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// 结束进程组，进程组 ID 即子进程的 ID
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // SAFETY: 只向该命令的进程组发送信号
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// 解析命令输出，以 `[` 开头时按 JSON 解析，否则每行一个节点
+fn parse(group: &str, output: &str) -> Result<DynamicNodes> {
+    let mut nodes = DynamicNodes::default();
+    if output.trim_start().starts_with('[') {
+        let json: Vec<JsonNode> = serde_json::from_str(output)
+            .map_err(|e| BeelogError::Config(tr!(NodesCmdInvalidJson, group, e)))?;
+        for node in json {
+            match node {
+                JsonNode::Name(name) => nodes.nodes.push(name),
+                JsonNode::Node { name, host } => {
+                    if let Some(host) = host {
+                        nodes.expected_hosts.insert(name.clone(), host);
+                    }
+                    nodes.nodes.push(name);
+                }
+            }
+        }
+    } else {
+        nodes.nodes = output.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+    }
+    Ok(nodes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_parse() {
+        let nodes = parse("g", "app-01\n\n# comment\n  app-02  \n").unwrap();
+        assert_eq!(nodes.nodes, ["app-01", "app-02"]);
+        let nodes = parse("g", r#"["app-01", {"name": "app-02", "host": "10.0.0.2"}]"#).unwrap();
+        assert_eq!(nodes.nodes, ["app-01", "app-02"]);
+        assert_eq!(nodes.expected_hosts.get("app-02").map(String::as_str), Some("10.0.0.2"));
+        assert!(matches!(parse("g", "[1, 2]"), Err(BeelogError::Config(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load() {
+        let dir = TempDir::new("nodes-cmd");
+        let (timeout, ttl) = (Duration::from_secs(5), Duration::from_secs(60));
+        let counter = dir.join("count");
+        let command = format!("echo x >> {}; printf 'app-01\\napp-02\\n'", counter.display());
+        assert_eq!(load("g", &command, timeout, ttl, &dir).unwrap().nodes, ["app-01", "app-02"]);
+        assert_eq!(load("g", &command, timeout, ttl, &dir).unwrap().nodes, ["app-01", "app-02"]);
+        assert_eq!(fs::read_to_string(&counter).unwrap().lines().count(), 1);
+
+        let error = load("g", "sleep 5", Duration::from_millis(100), ttl, &dir).unwrap_err();
+        assert!(matches!(error, BeelogError::Config(_)));
+        assert!(load("g", "echo oops >&2; exit 3", timeout, Duration::ZERO, &dir).unwrap_err().to_string().contains("oops"));
+
+        // ttl 为 0 时不使用过期的缓存
+        let flag = dir.join("flag");
+        let command = format!("test ! -e {} && echo app-01", flag.display());
+        assert_eq!(load("g", &command, timeout, ttl, &dir).unwrap().nodes, ["app-01"]);
+        fs::write(&flag, "").unwrap();
+        assert!(load("g", &command, timeout, Duration::ZERO, &dir).is_err());
+
+        // 超时后结束整个进程组
+        let leaked = dir.join("leaked");
+        let command = format!("(sleep 1; touch {}) & sleep 5", leaked.display());
+        assert!(load("g", &command, Duration::from_millis(100), Duration::ZERO, &dir).is_err());
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!leaked.exists());
+    }
+}
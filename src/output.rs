@@ -0,0 +1,86 @@
+use std::io::{self, IsTerminal};
+use console::Style;
+
+
+/// 节点前缀可用的颜色
+const PREFIX_COLORS: [fn(Style) -> Style; 6] = [Style::cyan, Style::green, Style::yellow, Style::blue, Style::magenta, Style::red];
+
+/**
+ * 按行添加节点前缀，输出格式为 `node: line`
+ * 可逐块输入流式输出，只返回已完整的行；第一行为命令回显，不输出
+ * 末尾未换行的内容（通常是命令提示符）保留在缓冲中
+ */
+pub struct LinePrefixer {
+    prefix: String,
+    buf: String,
+    echo_skipped: bool,
+}
+
+impl LinePrefixer {
+
+    /// stdout 为终端时，前缀使用按节点名称固定的颜色
+    pub fn new(node: &str) -> Self {
+        let prefix = if io::stdout().is_terminal() {
+            node_style(node).apply_to(format!("{}:", node)).to_string()
+        } else {
+            format!("{}:", node)
+        };
+        LinePrefixer {
+            prefix,
+            buf: String::new(),
+            echo_skipped: false,
+        }
+    }
+
+    /// 输入新的输出，返回已完整且添加了前缀的行
+    pub fn feed(&mut self, chunk: &str) -> Vec<String> {
+        self.buf.push_str(chunk);
+        let Some(end) = self.buf.rfind('\n') else {
+            return Vec::new();
+        };
+        let complete: String = self.buf.drain(..=end).collect();
+        let mut lines = Vec::new();
+        for line in complete.lines() {
+            if !self.echo_skipped {
+                self.echo_skipped = true;
+                continue;
+            }
+            lines.push(format!("{} {}", self.prefix, line.trim_end_matches('\r')));
+        }
+        lines
+    }
+
+    /// 返回缓冲中剩余的未换行内容
+    pub fn flush(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buf);
+        let rest = rest.trim_end_matches('\r');
+        (!rest.is_empty() && self.echo_skipped).then(|| format!("{} {}", self.prefix, rest))
+    }
+}
+
+/// 节点名称对应的固定颜色
+fn node_style(node: &str) -> Style {
+    // FNV-1a，保证每次运行颜色一致
+    let hash = node.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    PREFIX_COLORS[(hash % PREFIX_COLORS.len() as u64) as usize](Style::new())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_prefixer_feed() {
+        let mut prefixer = LinePrefixer {
+            prefix: "node1:".to_string(),
+            buf: String::new(),
+            echo_skipped: false,
+        };
+        assert!(prefixer.feed("wc -l a.log\r\n12").is_empty());
+        assert_eq!(prefixer.feed("3 a.log\r\n[root@node1 ~]# "), vec!["node1: 123 a.log"]);
+        assert_eq!(prefixer.flush(), Some("node1: [root@node1 ~]# ".to_string()));
+        assert_eq!(prefixer.flush(), None);
+        assert_eq!(node_style("node1"), node_style("node1"));
+    }
+}
@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+/// 已登记的敏感内容，记录日志前替换
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+/// 替换后的内容
+const MASK: &str = "******";
+/// 过短的内容容易误伤正常输出，不登记
+const MIN_SECRET_LEN: usize = 4;
+
+/// 登记密码、私钥密码、MFA 密钥等长期有效的内容，之后的日志中将被替换
+pub fn register(secret: &str) {
+    if secret.len() < MIN_SECRET_LEN {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(|e| e.into_inner());
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// 替换文本中已登记的敏感内容
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS.read().unwrap_or_else(|e| e.into_inner());
+    secrets.iter().fold(text.to_string(), |text, secret| text.replace(secret.as_str(), MASK))
+}
+
+/// 原始字节转为单行可读文本，控制字符转义，并替换敏感内容
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    redact(&String::from_utf8_lossy(bytes)).escape_debug().to_string()
+}
+
+/// 隐藏 `export NAME=value` 中的值，env 中可能包含令牌等未登记的内容
+pub fn mask_export(command: &str) -> Cow<'_, str> {
+    match command.strip_prefix("export ").and_then(|rest| rest.split_once('=')) {
+        Some((name, _)) => Cow::Owned(format!("export {}={}", name, MASK)),
+        None => Cow::Borrowed(command),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        register("s3cr3t-pass");
+        register("abc");
+        assert_eq!(redact("password is s3cr3t-pass!"), "password is ******!");
+        assert_eq!(redact("abc"), "abc");
+        assert_eq!(escape_bytes(b"Opt> s3cr3t-pass\r\n"), "Opt> ******\\r\\n");
+    }
+
+    #[test]
+    fn test_mask_export() {
+        assert_eq!(mask_export("export API_TOKEN='a=b c'"), "export API_TOKEN=******");
+        assert_eq!(mask_export("cd /data"), "cd /data");
+    }
+}
@@ -0,0 +1,256 @@
+//! 节点标签选择表达式
+//!
+//! ```text
+//! role=web and dc!=bj
+//! (role=web or role=api) and not canary
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use crate::error::{BeelogError, Result};
+use crate::tr;
+
+/// 标签选择表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    /// 带有该标签，`canary`
+    Has(String),
+    /// 标签等于该值，`role=web`
+    Eq(String, String),
+    /// 标签不等于该值或没有该标签，`dc!=bj`
+    Ne(String, String),
+    Not(Box<Selector>),
+    And(Box<Selector>, Box<Selector>),
+    Or(Box<Selector>, Box<Selector>),
+}
+
+impl Selector {
+
+    /**
+     * 解析表达式
+     * 优先级从高到低为 `not`、`and`、`or`，可用括号分组；值含空格等字符时使用引号
+     */
+    pub fn parse(expr: &str) -> Result<Self> {
+        let tokens = tokenize(expr)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0, expr };
+        let selector = parser.or()?;
+        match parser.peek() {
+            None => Ok(selector),
+            Some(token) => Err(parser.error(token)),
+        }
+    }
+
+    /// 节点标签是否满足表达式
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Selector::Has(key) => labels.contains_key(key),
+            Selector::Eq(key, value) => labels.get(key) == Some(value),
+            Selector::Ne(key, value) => labels.get(key) != Some(value),
+            Selector::Not(selector) => !selector.matches(labels),
+            Selector::And(left, right) => left.matches(labels) && right.matches(labels),
+            Selector::Or(left, right) => left.matches(labels) || right.matches(labels),
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = BeelogError;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        Selector::parse(expr)
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Has(key) => write!(f, "{}", key),
+            Selector::Eq(key, value) => write!(f, "{}={}", key, value),
+            Selector::Ne(key, value) => write!(f, "{}!={}", key, value),
+            Selector::Not(selector) => write!(f, "not {}", selector),
+            Selector::And(left, right) => write!(f, "({} and {})", left, right),
+            Selector::Or(left, right) => write!(f, "({} or {})", left, right),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Eq,
+    Ne,
+    Open,
+    Close,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => f.write_str(word),
+            Token::Eq => f.write_str("="),
+            Token::Ne => f.write_str("!="),
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+        }
+    }
+}
+
+/// 拆分为单词、运算符及括号，引号内的内容作为一个单词
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '=' => tokens.push(Token::Eq),
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                tokens.push(Token::Ne);
+            }
+            '"' | '\'' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err(BeelogError::Config(tr!(SelectUnclosedQuote, expr))),
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || matches!(ch, '(' | ')' | '=' | '!' | '"' | '\'') {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// 递归下降解析
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    expr: &'a str,
+}
+
+impl Parser<'_> {
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// 下一个单词是否为指定关键字
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let matched = matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn error(&self, token: &Token) -> BeelogError {
+        BeelogError::Config(tr!(SelectUnexpected, token, self.expr))
+    }
+
+    fn or(&mut self) -> Result<Selector> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Selector::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Selector> {
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            left = Selector::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Selector> {
+        if self.keyword("not") {
+            return Ok(Selector::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Selector> {
+        match self.next() {
+            Some(Token::Open) => {
+                let selector = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(selector),
+                    Some(token) => Err(self.error(&token)),
+                    None => Err(BeelogError::Config(tr!(SelectIncomplete, self.expr))),
+                }
+            }
+            Some(Token::Word(key)) => {
+                let operator = match self.peek() {
+                    Some(Token::Eq) => Token::Eq,
+                    Some(Token::Ne) => Token::Ne,
+                    _ => return Ok(Selector::Has(key)),
+                };
+                self.pos += 1;
+                match (operator, self.next()) {
+                    (Token::Eq, Some(Token::Word(value))) => Ok(Selector::Eq(key, value)),
+                    (_, Some(Token::Word(value))) => Ok(Selector::Ne(key, value)),
+                    (_, Some(token)) => Err(self.error(&token)),
+                    (_, None) => Err(BeelogError::Config(tr!(SelectIncomplete, self.expr))),
+                }
+            }
+            Some(token) => Err(self.error(&token)),
+            None => Err(BeelogError::Config(tr!(SelectIncomplete, self.expr))),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let selector = Selector::parse("role=web and dc!=bj or not canary").unwrap();
+        assert_eq!(selector.to_string(), "((role=web and dc!=bj) or not canary)");
+        let selector = Selector::parse("role = 'web app' AND (dc=sh OR dc=bj)").unwrap();
+        assert_eq!(selector.to_string(), "(role=web app and (dc=sh or dc=bj))");
+        for invalid in ["", "role=", "(role=web", "role=web)", "role=web and", "= web", "role='web"] {
+            assert!(matches!(Selector::parse(invalid), Err(BeelogError::Config(_))), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let selector = Selector::parse("role=web and dc!=bj").unwrap();
+        assert!(selector.matches(&labels(&[("role", "web"), ("dc", "sh")])));
+        assert!(selector.matches(&labels(&[("role", "web")])));
+        assert!(!selector.matches(&labels(&[("role", "web"), ("dc", "bj")])));
+        assert!(!selector.matches(&labels(&[("role", "db")])));
+        let selector = Selector::parse("not canary").unwrap();
+        assert!(selector.matches(&labels(&[])));
+        assert!(!selector.matches(&labels(&[("canary", "true")])));
+    }
+}
@@ -1,191 +1,557 @@
-use ssh2::{Channel, Session, KeyboardInteractivePrompt};
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream};
-use std::path::Path;
-use std::time::{Duration, Instant};
-use std::io::{Write, Read};
+use ssh2::{Channel, Session};
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+use std::io::{self, ErrorKind, Write, Read};
 use std::ops::Not;
 use std::string::ToString;
-use anyhow::{Result, Error, anyhow};
-use encoding_rs::{DecoderResult, UTF_8};
+use encoding_rs::{Decoder, UTF_8};
+use tokio::io::Interest;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use crate::auth::{self, Credentials};
 use crate::config::ServerInfo;
-use crate::mfa;
+use crate::error::{BeelogError, Result};
+use crate::interaction::Interaction;
+use crate::known_hosts;
+use crate::redact;
+use crate::tr;
 
 
-const MFA_MARK : &str = "OTP Code";
+/// 没有socket事件时的兜底轮询间隔
+const POLL_INTERVAL_MILLIS: u64 = 50;
+/// 开启/关闭通道等非阻塞操作的超时
+const IO_TIMEOUT_SECS: u64 = 10;
+/// 单次读取的缓冲大小
+const READ_BUF_SIZE: usize = 16 * 1024;
+/// 没有等待中的命令时，最多保留的输出
+const IDLE_BUF_SIZE: usize = 64 * 1024;
 
-/// MFA交互结构
-struct MfaKeyboardPrompt {
-    /// MFA code
-    secret_code: String,
-}
+/// 中断命令的控制字符
+const CTRL_C: &str = "\x03";
+/// 中断后等待回到提示符的最长时间
+pub const INTERRUPT_DRAIN_SECS: u64 = 5;
+/// 超时日志中记录的输出末尾长度
+const LOG_TAIL_LEN: usize = 200;
 
-impl MfaKeyboardPrompt {
-    fn new (secret_code: &str) -> Self {
-        MfaKeyboardPrompt {
-            secret_code: secret_code.to_string(),
-        }
-    }
+/// 会话请求，由会话的I/O任务统一处理
+enum Request {
+    Open(oneshot::Sender<Result<usize>>),
+    Exec {
+        id: usize,
+        command: Option<String>,
+        prompts: Vec<String>,
+        timeout: Duration,
+        /// 实时转发解码后的输出
+        output: Option<mpsc::UnboundedSender<String>>,
+        reply: oneshot::Sender<Result<(String, String)>>,
+    },
+    Interrupt {
+        id: usize,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Close {
+        id: usize,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Disconnect(oneshot::Sender<Result<()>>),
 }
 
-/// MFA交互实现
-impl KeyboardInteractivePrompt for MfaKeyboardPrompt {
-    fn prompt(
-        &mut self,
-        _username: &str,
-        _instructions: &str,
-        prompts: &[ssh2::Prompt<'_>]
-    ) -> Vec<String> {
-        let mut responses = Vec::new();
-        for prompt in prompts {
-            if prompt.text.contains(MFA_MARK) {
-                let mfa_code = mfa::get_google_code(&self.secret_code);
-                responses.push(mfa_code);
-            } else {
-                println!("未知的认证方式：{}", prompt.text);
-            }
-        }
-        responses
-    }
+/**
+ * ssh会话
+ * 会话处于非阻塞模式，由一个I/O任务负责所有通道的读写，socket可读时立即处理
+ * 句柄可克隆，在多个节点间共享
+ */
+#[derive(Clone)]
+pub struct SshSession {
+    tx: mpsc::UnboundedSender<Request>,
 }
 
-/// ssh连接结构体
+/// ssh连接结构体，对应会话上的一个shell通道
+#[derive(Clone)]
 pub struct SshBridge {
-    session: Session,
-    channel: Channel,
+    id: usize,
+    tx: mpsc::UnboundedSender<Request>,
 }
 
-/// ssh连接实现
-impl SshBridge {
+impl SshSession {
 
-    /// 建立连接
-    pub fn create_bridge(server_info: ServerInfo, prompts: &str) -> Result<Self, Error> {
-        let host_split: Vec<u8> = server_info.host.split(".")
-            .map(|e| {e.parse::<u8>().expect(&format!("Host转换错误: {} - {}", server_info.host, e))})
-            .collect::<Vec<u8>>();
-        if host_split.len() != 4 {
-            return Err(anyhow!("无效的 IP 地址"));
-        }
-        let socket = SocketAddrV4::new(Ipv4Addr::new(host_split[0], host_split[1], host_split[2], host_split[3]), server_info.port);
-        let tcp = TcpStream::connect_timeout(&SocketAddr::V4(socket), Duration::from_secs(20)).map_err(|e| anyhow!(format!("连接失败: {}", e)))?;
-        let mut sess = Session::new().map_err(|e| anyhow!(format!("创建 session 失败: {}", e)))?;
+    /// 建立会话并启动I/O任务
+    pub async fn connect(server_info: ServerInfo, credentials: Arc<Credentials>, interaction: Arc<dyn Interaction>) -> Result<Self> {
+        let (session, tcp) = tokio::task::spawn_blocking(move || {
+            Self::create_session(&server_info, &credentials, interaction.as_ref())
+        }).await.map_err(|e| BeelogError::Connect(e.to_string()))??;
+        session.set_blocking(false);
+        let stream = tcp.set_nonblocking(true)
+            .and_then(|_| tokio::net::TcpStream::from_std(tcp))
+            .map_err(|e| BeelogError::Connect(e.to_string()))?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let driver = SessionDriver {
+            session,
+            stream,
+            rx,
+            channels: HashMap::new(),
+            next_id: 0,
+        };
+        tokio::spawn(driver.run());
+        Ok(SshSession { tx })
+    }
+
+    /**
+     * 建立ssh会话并完成认证
+     * 同一会话可开启多个shell通道，多个节点共用一次登录
+     * 主机名解析出多个地址时依次尝试，返回会话及socket副本，副本仅用于等待可读事件
+     */
+    fn create_session(server_info: &ServerInfo, credentials: &Credentials, interaction: &dyn Interaction) -> Result<(Session, TcpStream)> {
+        let host = server_info.host.as_str();
+        let started = Instant::now();
+        let addrs: Vec<_> = (host, server_info.port).to_socket_addrs()
+            .map_err(|e| BeelogError::Resolve(format!("{}: {}", host, e)))?
+            .collect();
+        tracing::debug!(host, ?addrs, elapsed = ?started.elapsed(), "地址解析完成");
+        let timeouts = &server_info.timeouts;
+        let mut last_error = BeelogError::Resolve(tr!(NoAddress, host));
+        let tcp = addrs.iter()
+            .find_map(|addr| TcpStream::connect_timeout(addr, Duration::from_secs(timeouts.tcp))
+                .inspect(|_| tracing::debug!(%addr, elapsed = ?started.elapsed(), "TCP 连接成功"))
+                .map_err(|e| {
+                    tracing::debug!(%addr, error = %e, "TCP 连接失败");
+                    last_error = BeelogError::Connect(format!("{}: {}", addr, e));
+                })
+                .ok())
+            .ok_or(last_error)?;
+        // 交互式会话，关闭 Nagle 算法避免小包延迟
+        let _ = tcp.set_nodelay(true);
+        let watcher = tcp.try_clone().map_err(|e| BeelogError::Connect(e.to_string()))?;
+        let mut sess = Session::new().map_err(|e| BeelogError::Connect(tr!(SessionCreateFailed, e)))?;
         sess.set_tcp_stream(tcp);
-        sess.set_timeout(1000 * 10);
-        sess.handshake().map_err(|e| anyhow!(format!("握手失败: {}", e)))?;
-
-        let pri_key_path = Path::new(&server_info.key_path);
-        let auth_pubkey_res = sess.userauth_pubkey_file(&server_info.user, None, pri_key_path, None);
-        if let Err(e) = auth_pubkey_res {
-            if let Some(secret_code) = &server_info.secret_code {
-                let mut prompt = MfaKeyboardPrompt::new(secret_code);
-                let auth_keyboard_res = sess.userauth_keyboard_interactive(&server_info.user, &mut prompt);
-                if let Err(e) = auth_keyboard_res {
-                    return Err(anyhow!(format!("二次认证失败: {}", e)));
-                }
-            } else {
-                return Err(anyhow!(format!("证书认证失败: {}", e)));
-            }
-        } 
+        sess.set_timeout(secs_to_millis(timeouts.handshake));
+        sess.handshake().map_err(|e| BeelogError::Connect(tr!(HandshakeFailed, e)))?;
+        tracing::debug!(banner = sess.banner(), elapsed = ?started.elapsed(), "握手完成");
+        sess.set_timeout(secs_to_millis(timeouts.auth));
+        known_hosts::verify_host_key(&sess, host, server_info.port, server_info.host_key_policy, interaction)?;
 
-        if !sess.authenticated() {
-            return Err(anyhow!("认证失败"));
-        }
+        auth::authenticate(&sess, server_info, credentials)?;
+        tracing::debug!(host, elapsed = ?started.elapsed(), "会话已认证");
+        Ok((sess, watcher))
+    }
 
-        let mut channel = sess.channel_session().map_err(|e| anyhow!(format!("创建 channel 失败: {}", e)))?;
-        channel.request_pty("xterm", None, None).map_err(|e| anyhow!(format!("PTY 请求失败: {}", e)))?;
-        // 开启 shell 模式
-        channel.shell().map_err(|e| anyhow!(format!("打开 shell 失败: {}", e)))?;
+    /// 在会话上开启shell通道
+    pub async fn open_bridge(&self) -> Result<SshBridge> {
+        let id = request(&self.tx, Request::Open).await?;
+        Ok(SshBridge {
+            id,
+            tx: self.tx.clone(),
+        })
+    }
+
+    /// 断开会话
+    pub async fn disconnect(&self) -> Result<()> {
+        request(&self.tx, Request::Disconnect).await
+    }
+}
+
+/// ssh连接实现
+impl SshBridge {
 
-        let (matched_prompt, _) = Self::wait_for_prompt(&mut channel, vec!(prompts.to_string()), 10)?;
+    /// 等待JumpServer菜单就绪
+    pub async fn wait_ready(&self, prompts: &str, timeout_secs: u64) -> Result<()> {
+        let (matched_prompt, _) = self.wait_for(vec!(prompts.to_string()), timeout_secs).await?;
         if matched_prompt.is_empty().not() {
             if matched_prompt != prompts  {
-                return Err(anyhow!("未能正确连接"));
+                return Err(BeelogError::Navigation(tr!(MenuMismatch)));
             }
         } else {
-            return Err(anyhow!("未能正确连接"));
+            return Err(BeelogError::Timeout(tr!(WaitMenu)));
         }
-        // 读取时不会阻塞
-        // sess.set_blocking(false);
-        Ok(SshBridge {
-            session: sess,
-            channel,
-        })
+        Ok(())
     }
 
-    /// 命令执行
-    pub fn exec(&mut self, command: &str, prompts: Vec<String>) -> Result<String, Error> {
-        Self::send_line(&mut self.channel, command)?;
-        // 等待登录目标主机
-        let (_, output) = Self::wait_for_prompt(&mut self.channel, prompts, 60 * 20)?;
-        Ok(output)
+    /// 命令执行，返回匹配到的关键字及输出，超时未匹配时关键字为空
+    pub async fn exec_with_timeout(&self, command: &str, prompts: Vec<String>, timeout_secs: u64) -> Result<(String, String)> {
+        self.request_output(Some(format!("{}\r", command)), prompts, timeout_secs, None).await
     }
 
-    /// 关闭连接
-    pub fn close(&mut self) -> Result<(), Error> {
-        let channel = &mut self.channel;
-        channel.send_eof()?;
-        channel.wait_eof()?;
-        channel.close()?;
-        channel.wait_close()?;
-        self.session.disconnect(None, "Close", None)?;
-        Ok(())
+    /// 命令执行，同时将收到的输出实时发送到 output
+    pub async fn exec_streaming(&self, command: &str, prompts: Vec<String>, timeout_secs: u64, output: mpsc::UnboundedSender<String>) -> Result<(String, String)> {
+        self.request_output(Some(format!("{}\r", command)), prompts, timeout_secs, Some(output)).await
+    }
+
+    /// 等待输出直到匹配关键字，不发送命令
+    pub async fn wait_for(&self, prompts: Vec<String>, timeout_secs: u64) -> Result<(String, String)> {
+        self.request_output(None, prompts, timeout_secs, None).await
     }
 
     /**
-     * 等待输出
+     * 中断正在执行的命令（发送 Ctrl-C）
+     * 等待中的命令继续读取直到回到提示符，但最多再等待 [`INTERRUPT_DRAIN_SECS`] 秒
      */
-    fn wait_for_prompt(channel: &mut Channel, prompts: Vec<String>, timeout_secs: u64) -> Result<(String, String), Error> {
-        let deadline = Instant::now() + Duration::from_secs(timeout_secs);
-        // 匹配到的关键字
-        let mut matched_prompt = String::new();
-        let mut content = String::new();
-
-        let mut decoder = UTF_8.new_decoder();
-        let mut raw_buf = [0u8; 1024];
-        let mut read_buf = Vec::new(); // 用于保存残余字节
-        let mut decode_buf = String::with_capacity(2048);
-
-        'out_loop: while Instant::now() < deadline {
-            match channel.read(&mut raw_buf) {
-                Ok(n) => {
-                    if n == 0 {
-                        break;
-                    }
-                    // 追加到字节缓存中
-                    read_buf.extend_from_slice(&raw_buf[..n]);
+    pub async fn interrupt(&self) -> Result<()> {
+        request(&self.tx, |reply| Request::Interrupt { id: self.id, reply }).await
+    }
+
+    /// 发送 Ctrl-C 并等待回到提示符，用于命令超时后清理
+    pub async fn interrupt_and_wait(&self, prompts: Vec<String>, timeout_secs: u64) -> Result<(String, String)> {
+        self.request_output(Some(CTRL_C.to_string()), prompts, timeout_secs, None).await
+    }
+
+    /// 关闭通道，会话由 [`SshSession::disconnect`] 统一断开
+    pub async fn close(&self) -> Result<()> {
+        request(&self.tx, |reply| Request::Close { id: self.id, reply }).await
+    }
+
+    async fn request_output(&self, command: Option<String>, prompts: Vec<String>, timeout_secs: u64, output: Option<mpsc::UnboundedSender<String>>) -> Result<(String, String)> {
+        request(&self.tx, |reply| Request::Exec {
+            id: self.id,
+            command,
+            prompts,
+            timeout: Duration::from_secs(timeout_secs),
+            output,
+            reply,
+        }).await
+    }
+}
+
+/// 通道已关闭的错误
+fn channel_closed() -> BeelogError {
+    BeelogError::ChannelClosed(tr!(ChannelUnavailable))
+}
+
+/// 发送请求并等待I/O任务应答
+async fn request<T>(tx: &mpsc::UnboundedSender<Request>, build: impl FnOnce(oneshot::Sender<Result<T>>) -> Request) -> Result<T> {
+    let (reply, rx) = oneshot::channel();
+    tx.send(build(reply)).map_err(|_| BeelogError::ChannelClosed(tr!(SessionDisconnected)))?;
+    rx.await.map_err(|_| BeelogError::ChannelClosed(tr!(SessionDisconnected)))?
+}
+
+/// 会话I/O任务
+struct SessionDriver {
+    session: Session,
+    /// socket副本，用于等待可读事件
+    stream: tokio::net::TcpStream,
+    rx: mpsc::UnboundedReceiver<Request>,
+    channels: HashMap<usize, ChannelState>,
+    next_id: usize,
+}
+
+/// 通道状态
+struct ChannelState {
+    channel: Channel,
+    /// 待写入的命令
+    write_buf: Vec<u8>,
+    /// 正在等待关键字的输出
+    pending: Option<PendingOutput>,
+    /// 没有等待中的命令时收到的输出，发送新命令时丢弃
+    idle: Vec<u8>,
+    eof: bool,
+}
+
+/// 等待关键字的输出
+struct PendingOutput {
+    prompts: Vec<String>,
+    max_prompt_len: usize,
+    started: Instant,
+    deadline: Instant,
+    decoder: Decoder,
+    content: String,
+    output: Option<mpsc::UnboundedSender<String>>,
+    reply: oneshot::Sender<Result<(String, String)>>,
+}
+
+impl SessionDriver {
+
+    /**
+     * 事件循环
+     * 处理请求并轮询所有通道，没有进展时清除可读状态再轮询一次，
+     * 之后等待新请求、socket可读或兜底间隔
+     */
+    async fn run(mut self) {
+        let mut buf = vec![0u8; READ_BUF_SIZE];
+        loop {
+            while let Ok(req) = self.rx.try_recv() {
+                if !self.handle(req).await {
+                    return;
+                }
+            }
+            if self.poll_channels(&mut buf) {
+                continue;
+            }
+            // libssh2 自行读取socket，需要手动清除tokio记录的可读状态
+            let _ = self.stream.try_io(Interest::READABLE, || Err::<(), _>(ErrorKind::WouldBlock.into()));
+            if self.poll_channels(&mut buf) {
+                continue;
+            }
+            tokio::select! {
+                req = self.rx.recv() => match req {
+                    Some(req) => if !self.handle(req).await {
+                        return;
+                    },
+                    // 所有句柄已释放
+                    None => return,
+                },
+                _ = self.stream.readable() => {},
+                _ = tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MILLIS)) => {},
+            }
+        }
+    }
 
-                    // 使用 decoder 解码
-                    let (decoder_result, read) = decoder.decode_to_string_without_replacement(&read_buf, &mut decode_buf, false);
-                    if decoder_result != DecoderResult::InputEmpty {
-                        eprintln!("⚠️ 解码时发生错误！");
+    /// 处理请求，返回 false 表示会话已断开
+    async fn handle(&mut self, req: Request) -> bool {
+        match req {
+            Request::Open(reply) => {
+                let res = self.open_channel().await;
+                let _ = reply.send(res);
+            }
+            Request::Exec { id, command, prompts, timeout, output, reply } => {
+                let Some(state) = self.channels.get_mut(&id) else {
+                    let _ = reply.send(Err(channel_closed()));
+                    return true;
+                };
+                if state.eof {
+                    let _ = reply.send(Err(channel_closed()));
+                    return true;
+                }
+                // 命令中可能有 env 设置的令牌等未登记的内容，-v 只记录长度，-vv 记录原文
+                tracing::debug!(channel = id, command_len = command.as_ref().map(String::len), ?prompts, ?timeout, "等待关键字");
+                if let Some(command) = &command {
+                    tracing::trace!(channel = id, command = %redact::escape_bytes(command.as_bytes()), "发送命令");
+                }
+                let mut pending = PendingOutput::new(prompts, timeout, output, reply);
+                let idle = std::mem::take(&mut state.idle);
+                match command {
+                    Some(command) => state.write_buf.extend_from_slice(command.as_bytes()),
+                    // 只等待输出时，先匹配之前收到的内容
+                    None => if let Some(matched) = pending.feed(&idle) {
+                        let _ = pending.reply.send(Ok((matched, pending.content)));
+                        return true;
+                    },
+                }
+                state.pending = Some(pending);
+            }
+            Request::Interrupt { id, reply } => {
+                let res = match self.channels.get_mut(&id) {
+                    Some(state) if !state.eof => {
+                        state.write_buf.extend_from_slice(CTRL_C.as_bytes());
+                        if let Some(pending) = state.pending.as_mut() {
+                            pending.deadline = pending.deadline.min(Instant::now() + Duration::from_secs(INTERRUPT_DRAIN_SECS));
+                        }
+                        Ok(())
                     }
+                    _ => Err(channel_closed()),
+                };
+                let _ = reply.send(res);
+            }
+            Request::Close { id, reply } => {
+                let res = match self.channels.remove(&id) {
+                    Some(mut state) => self.close_channel(&mut state.channel).await,
+                    None => Ok(()),
+                };
+                let _ = reply.send(res);
+            }
+            Request::Disconnect(reply) => {
+                let session = &self.session;
+                let res = nonblocking(&self.stream, || session.disconnect(None, "Close", None)).await;
+                let _ = reply.send(res.map_err(|e| BeelogError::ChannelClosed(tr!(DisconnectFailed, e))));
+                return false;
+            }
+        }
+        true
+    }
 
-                    // 将成功读取的内容加入 content 中
-                    content.push_str(&decode_buf);
+    /// 开启shell通道
+    async fn open_channel(&mut self) -> Result<usize> {
+        let session = &self.session;
+        let mut channel = nonblocking(&self.stream, || session.channel_session()).await
+            .map_err(|e| BeelogError::Connect(tr!(ChannelOpenFailed, e)))?;
+        nonblocking(&self.stream, || channel.request_pty("xterm", None, None)).await
+            .map_err(|e| BeelogError::Connect(tr!(PtyFailed, e)))?;
+        // 开启 shell 模式
+        nonblocking(&self.stream, || channel.shell()).await
+            .map_err(|e| BeelogError::Connect(tr!(ShellFailed, e)))?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.channels.insert(id, ChannelState {
+            channel,
+            write_buf: Vec::new(),
+            pending: None,
+            idle: Vec::new(),
+            eof: false,
+        });
+        Ok(id)
+    }
+
+    async fn close_channel(&self, channel: &mut Channel) -> Result<()> {
+        let close_error = |e: io::Error| BeelogError::ChannelClosed(tr!(ChannelCloseFailed, e));
+        nonblocking(&self.stream, || channel.send_eof()).await.map_err(close_error)?;
+        nonblocking(&self.stream, || channel.close()).await.map_err(close_error)?;
+        nonblocking(&self.stream, || channel.wait_close()).await.map_err(close_error)?;
+        Ok(())
+    }
+
+    /// 轮询所有通道，返回是否有进展
+    fn poll_channels(&mut self, buf: &mut [u8]) -> bool {
+        let mut progressed = false;
+        for (id, state) in self.channels.iter_mut() {
+            progressed |= state.poll(*id, buf);
+        }
+        progressed
+    }
+}
 
-                    // 移除已读的字节
-                    read_buf.drain(..read);
-                    
-                    for prompt in prompts.iter() {
-                        if decode_buf.contains(prompt) {
-                            matched_prompt = prompt.clone();
-                            break 'out_loop;
+impl ChannelState {
+
+    /// 写入待发送的命令并读取输出，返回是否有进展
+    fn poll(&mut self, id: usize, buf: &mut [u8]) -> bool {
+        let mut progressed = false;
+        while !self.write_buf.is_empty() && !self.eof {
+            match self.channel.write(&self.write_buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.write_buf.drain(..n);
+                    progressed = true;
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    self.write_buf.clear();
+                    self.fail(BeelogError::ChannelClosed(tr!(WriteFailed, e)));
+                    break;
+                }
+            }
+        }
+        while !self.eof {
+            match self.channel.read(buf) {
+                Ok(0) => {
+                    if self.channel.eof() {
+                        self.eof = true;
+                        self.finish(String::new());
+                        progressed = true;
+                    }
+                    break;
+                }
+                Ok(n) => {
+                    progressed = true;
+                    tracing::trace!(channel = id, bytes = %redact::escape_bytes(&buf[..n]), "收到输出");
+                    match self.pending.as_mut() {
+                        Some(pending) => if let Some(matched) = pending.feed(&buf[..n]) {
+                            self.finish(matched);
+                        },
+                        None => {
+                            self.idle.extend_from_slice(&buf[..n]);
+                            let overflow = self.idle.len().saturating_sub(IDLE_BUF_SIZE);
+                            self.idle.drain(..overflow);
                         }
                     }
-                    decode_buf.clear();
                 }
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    std::thread::sleep(Duration::from_millis(300));
-                    continue
-                },
-                Err(e) => return Err(anyhow!(e)),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    self.eof = true;
+                    self.fail(BeelogError::ChannelClosed(tr!(ReadFailed, e)));
+                    break;
+                }
+            }
+        }
+        if self.pending.as_ref().is_some_and(|pending| Instant::now() >= pending.deadline) {
+            if let Some(pending) = &self.pending {
+                tracing::debug!(channel = id, prompts = ?pending.prompts, tail = %tail(&pending.content), "等待关键字超时");
             }
+            self.finish(String::new());
         }
-        Ok((matched_prompt, content.to_string()))
+        progressed
     }
 
-    fn send_line(channel: &mut Channel, input: &str) -> Result<(), Error> {
-        channel.write_all(format!("{}\r", input).as_bytes())
-            .map_err(|e| anyhow!(format!("写入失败: {}", e)))?;
-        channel.flush().map_err(|e| anyhow!(format!("flush失败: {}", e)))
+    /// 应答等待中的命令
+    fn finish(&mut self, matched_prompt: String) {
+        if let Some(pending) = self.pending.take() {
+            tracing::debug!(matched = %matched_prompt, elapsed = ?pending.started.elapsed(), received = pending.content.len(), "等待结束");
+            let _ = pending.reply.send(Ok((matched_prompt, pending.content)));
+        }
+    }
+
+    fn fail(&mut self, error: BeelogError) {
+        if let Some(pending) = self.pending.take() {
+            let _ = pending.reply.send(Err(error));
+        }
     }
-}
\ No newline at end of file
+}
+
+impl PendingOutput {
+
+    fn new(prompts: Vec<String>, timeout: Duration, output: Option<mpsc::UnboundedSender<String>>, reply: oneshot::Sender<Result<(String, String)>>) -> Self {
+        let max_prompt_len = prompts.iter().map(|p| p.len()).max().unwrap_or(0);
+        PendingOutput {
+            prompts,
+            max_prompt_len,
+            started: Instant::now(),
+            deadline: Instant::now() + timeout,
+            decoder: UTF_8.new_decoder(),
+            content: String::new(),
+            output,
+            reply,
+        }
+    }
+
+    /// 解码新读取的内容，返回匹配到的关键字
+    fn feed(&mut self, bytes: &[u8]) -> Option<String> {
+        // 关键字可能跨越两次读取，从上次内容的尾部开始匹配
+        let mut search_from = self.content.len().saturating_sub(self.max_prompt_len);
+        while !self.content.is_char_boundary(search_from) {
+            search_from -= 1;
+        }
+        // decoder 会保留不完整的多字节字符，等待下次读取；只写入已有容量，需预留空间
+        let decoded_from = self.content.len();
+        let needed = self.decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(bytes.len() * 3);
+        self.content.reserve(needed);
+        // 无法解码的字节替换为 U+FFFD，不中断命令
+        let _ = self.decoder.decode_to_string(bytes, &mut self.content, false);
+        if let Some(output) = &self.output
+            && self.content.len() > decoded_from {
+            let _ = output.send(self.content[decoded_from..].to_string());
+        }
+        self.prompts.iter()
+            .find(|prompt| self.content[search_from..].contains(prompt.as_str()))
+            .cloned()
+    }
+}
+
+/// 输出末尾的一段，用于记录超时时实际停留的位置
+fn tail(content: &str) -> String {
+    let start = content.len().saturating_sub(LOG_TAIL_LEN);
+    redact::escape_bytes(&content.as_bytes()[start..])
+}
+
+/// libssh2 超时参数，单位毫秒
+fn secs_to_millis(secs: u64) -> u32 {
+    u32::try_from(secs.saturating_mul(1000)).unwrap_or(u32::MAX)
+}
+
+/// 非阻塞模式下重试，等待socket可读或兜底间隔后再次尝试，直到完成或超时
+async fn nonblocking<T>(stream: &tokio::net::TcpStream, mut op: impl FnMut() -> Result<T, ssh2::Error>) -> io::Result<T> {
+    let deadline = Instant::now() + Duration::from_secs(IO_TIMEOUT_SECS);
+    loop {
+        match op().map_err(io::Error::from) {
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => {
+                let _ = tokio::time::timeout(Duration::from_millis(POLL_INTERVAL_MILLIS), stream.readable()).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_output_feed() {
+        let (reply, _rx) = oneshot::channel();
+        let mut pending = PendingOutput::new(vec!["[root@node1".to_string()], Duration::from_secs(1), None, reply);
+        let text = "日志\r\n[root@node1 ~]# ".as_bytes();
+        // 多字节字符及关键字均被拆分到两次读取中
+        assert_eq!(pending.feed(&text[..4]), None);
+        assert_eq!(pending.feed(&text[4..12]), None);
+        assert_eq!(pending.feed(&text[12..]), Some("[root@node1".to_string()));
+        assert_eq!(pending.content, "日志\r\n[root@node1 ~]# ");
+    }
+}
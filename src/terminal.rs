@@ -0,0 +1,310 @@
+use beelog::config::OutputFormat;
+use dialoguer::FuzzySelect;
+use beelog::i18n::Msg;
+use beelog::tr;
+use beelog::error::BeelogError;
+use beelog::interaction::Interaction;
+use beelog::jump_server_helper::{ExecStatus, NodeResult, Progress};
+use beelog::output::LinePrefixer;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 节点进度行的刷新间隔
+const SPINNER_TICK_MILLIS: u64 = 100;
+
+
+/// 终端交互：在 stderr 提示，非交互终端不信任未知的服务器公钥、不做选择
+pub struct TerminalInteraction;
+
+impl Interaction for TerminalInteraction {
+
+    fn confirm_host_key(&self, host: &str, port: u16, key_type: &str, fingerprint: &str) -> bool {
+        eprintln!("{}", tr!(HostKeyUnknown, host, port));
+        eprintln!("{}", tr!(HostKeyFingerprint, key_type, fingerprint));
+        if !io::stdin().is_terminal() {
+            eprintln!("{}", tr!(HostKeyCannotConfirm));
+            return false;
+        }
+        loop {
+            eprint!("{}", tr!(HostKeyConfirm));
+            let _ = io::stderr().flush();
+            let mut answer = String::new();
+            if io::stdin().lock().read_line(&mut answer).is_err() {
+                return false;
+            }
+            match answer.trim().to_ascii_lowercase().as_str() {
+                "yes" | "y" => return true,
+                "no" | "n" | "" => return false,
+                _ => continue,
+            }
+        }
+    }
+
+    fn key_passphrase(&self, key_path: &Path) -> Option<String> {
+        rpassword::prompt_password(tr!(PassphrasePrompt, key_path.display())).ok()
+    }
+
+    fn choose(&self, prompt: &str, options: &[String]) -> Option<usize> {
+        if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+            return None;
+        }
+        FuzzySelect::new()
+            .with_prompt(prompt)
+            .items(options)
+            .default(0)
+            .interact_opt()
+            .ok()
+            .flatten()
+    }
+}
+
+/// 多个节点结果的输出顺序
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputOrder {
+    /// 全部结束后按节点顺序输出
+    #[default]
+    Ordered,
+    /// 每个节点结束即输出
+    FirstCome,
+}
+
+/**
+ * 终端进度显示及结果输出
+ * 连接、关闭显示总进度条；执行命令时每个节点显示一个进度行（耗时、已接收字节数、完成状态）
+ * 按完成顺序输出时每个节点结束即输出，若同时为行前缀格式，则每收到完整的一行即输出
+ */
+#[derive(Default)]
+pub struct TerminalProgress {
+    pub order: OutputOrder,
+    pub format: OutputFormat,
+    bar: Mutex<Option<ProgressBar>>,
+    exec: Mutex<Option<ExecView>>,
+}
+
+/// 命令执行中的进度显示
+struct ExecView {
+    mp: MultiProgress,
+    total: ProgressBar,
+    nodes: HashMap<String, NodeView>,
+}
+
+/// 单个节点的进度行
+struct NodeView {
+    pb: ProgressBar,
+    received: u64,
+    prefixer: LinePrefixer,
+}
+
+impl TerminalProgress {
+
+    pub fn new(format: OutputFormat) -> Self {
+        TerminalProgress {
+            format,
+            ..Default::default()
+        }
+    }
+
+    /// 是否逐行输出
+    fn streamed(&self) -> bool {
+        self.format == OutputFormat::Prefix && self.order == OutputOrder::FirstCome
+    }
+
+    /**
+     * 命令执行结束后输出结果
+     * 按节点顺序输出时在此统一输出，最后汇总超时及被取消的节点
+     */
+    pub fn print_results(&self, results: &[NodeResult], timeout_secs: u64) {
+        if self.order == OutputOrder::Ordered {
+            for result in results {
+                self.print_result(result);
+            }
+        }
+        let nodes_with = |status: ExecStatus| results.iter()
+            .filter(|r| r.result.as_ref().is_ok_and(|output| output.status == status))
+            .map(|r| r.node.as_str())
+            .collect::<Vec<_>>();
+        let timed_out = nodes_with(ExecStatus::TimedOut);
+        if !timed_out.is_empty() {
+            println!("{}", tr!(TimedOutNodes, timeout_secs, timed_out.join(", ")));
+        }
+        let cancelled = nodes_with(ExecStatus::Cancelled);
+        if !cancelled.is_empty() {
+            println!("{}", tr!(CancelledNodes, cancelled.join(", ")));
+        }
+    }
+
+    /// 输出单个节点的结果
+    fn print_result(&self, result: &NodeResult) {
+        let node = &result.node;
+        match (&result.result, self.format) {
+            (Ok(output), OutputFormat::Banner) => {
+                println!("======{}=======", node);
+                println!("{}", output.output);
+            }
+            (Ok(output), OutputFormat::Prefix) => {
+                let mut prefixer = LinePrefixer::new(node);
+                for line in prefixer.feed(&output.output) {
+                    println!("{}", line);
+                }
+                if let (ExecStatus::TimedOut, Some(rest)) = (output.status, prefixer.flush()) {
+                    println!("{}", rest);
+                }
+            }
+            (Err(e), _) => print_node_error(node, &tr!(ActionExec), e),
+        }
+    }
+
+    /// 设置总进度条
+    fn start_bar(&self, len: usize, prefix: &str) {
+        let pb = default_progress_bar(len as u64, Some(prefix.to_string()));
+        *self.bar.lock().unwrap_or_else(|e| e.into_inner()) = Some(pb);
+    }
+
+    fn inc_bar(&self) {
+        if let Some(pb) = self.bar.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            pb.inc(1);
+        }
+    }
+
+    fn finish_bar(&self, message: &'static str) {
+        if let Some(pb) = self.bar.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            pb.finish_with_message(message);
+        }
+    }
+
+    /// 更新节点进度行
+    fn with_node(&self, node: &str, f: impl FnOnce(&MultiProgress, &ProgressBar, &mut NodeView)) {
+        let mut exec = self.exec.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(view) = exec.as_mut()
+            && let Some(node_view) = view.nodes.get_mut(node) {
+            f(&view.mp, &view.total, node_view);
+        }
+    }
+}
+
+impl Progress for TerminalProgress {
+
+    fn connect_started(&self, nodes: &[String]) {
+        self.start_bar(nodes.len(), Msg::BarConnect.text());
+    }
+
+    fn node_connected(&self, _node: &str, _error: Option<&BeelogError>) {
+        self.inc_bar();
+    }
+
+    fn connect_finished(&self) {
+        self.finish_bar(Msg::BarConnected.text());
+    }
+
+    fn exec_started(&self, nodes: &[String]) {
+        let mp = MultiProgress::new();
+        let total = mp.add(default_progress_bar(nodes.len() as u64, Some(tr!(BarExec))));
+        let nodes = nodes.iter().map(|node| {
+            let node_view = NodeView {
+                pb: mp.add(node_spinner(node)),
+                received: 0,
+                prefixer: LinePrefixer::new(node),
+            };
+            (node.clone(), node_view)
+        }).collect();
+        *self.exec.lock().unwrap_or_else(|e| e.into_inner()) = Some(ExecView { mp, total, nodes });
+    }
+
+    fn node_output(&self, node: &str, chunk: &str) {
+        let streamed = self.streamed();
+        self.with_node(node, |mp, _, view| {
+            view.received += chunk.len() as u64;
+            view.pb.set_message(tr!(NodeReceived, HumanBytes(view.received)));
+            if streamed {
+                let lines = view.prefixer.feed(chunk);
+                if !lines.is_empty() {
+                    mp.suspend(|| lines.iter().for_each(|line| println!("{}", line)));
+                }
+            }
+        });
+    }
+
+    fn node_interrupting(&self, node: &str) {
+        self.with_node(node, |_, _, view| {
+            view.pb.set_message(tr!(NodeInterrupting, HumanBytes(view.received)));
+        });
+    }
+
+    fn node_finished(&self, result: &NodeResult) {
+        let streamed = self.streamed();
+        self.with_node(&result.node, |mp, total, view| {
+            let received = HumanBytes(view.received);
+            match &result.result {
+                Ok(output) => match output.status {
+                    ExecStatus::Completed => view.pb.finish_with_message(tr!(NodeCompleted, received)),
+                    ExecStatus::TimedOut => view.pb.finish_with_message(tr!(NodeTimedOut, received)),
+                    ExecStatus::Cancelled => view.pb.finish_with_message(tr!(NodeCancelled, received)),
+                },
+                Err(e) => view.pb.finish_with_message(tr!(NodeFailed, e)),
+            }
+            total.inc(1);
+            if self.order != OutputOrder::FirstCome {
+                return;
+            }
+            mp.suspend(|| match (&result.result, streamed) {
+                (Ok(output), true) => {
+                    if let (ExecStatus::TimedOut, Some(rest)) = (output.status, view.prefixer.flush()) {
+                        println!("{}", rest);
+                    }
+                }
+                _ => self.print_result(result),
+            });
+        });
+    }
+
+    fn exec_finished(&self) {
+        if let Some(view) = self.exec.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            view.total.finish();
+            let _ = view.mp.clear();
+        }
+    }
+
+    fn close_started(&self, nodes: &[String]) {
+        self.start_bar(nodes.len(), Msg::BarClose.text());
+    }
+
+    fn node_closed(&self, _node: &str, _error: Option<&BeelogError>) {
+        self.inc_bar();
+    }
+
+    fn close_finished(&self) {
+        self.finish_bar(Msg::BarClosed.text());
+    }
+}
+
+/// 输出节点错误
+pub fn print_node_error(node: &str, action: &str, error: &BeelogError) {
+    println!("{} > {}: {}", node, action, error);
+}
+
+/// 节点进度行，显示耗时及状态
+fn node_spinner(node: &str) -> ProgressBar {
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(ProgressStyle::default_spinner()
+        .template("{spinner:.green} {prefix:20} {elapsed:>4} {msg}").unwrap());
+    pb.set_prefix(node.to_string());
+    pb.set_message(Msg::NodeRunning.text());
+    pb.enable_steady_tick(Duration::from_millis(SPINNER_TICK_MILLIS));
+    pb
+}
+
+/// 默认进度条
+fn default_progress_bar(len: u64, prefix: Option<String>) -> ProgressBar {
+    let pb = ProgressBar::new(len);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{prefix} {bar:40.cyan/blue} {pos:>5}/{len:5} {msg}").unwrap()
+        .progress_chars("=> "));
+    if let Some(prefix) = prefix {
+        pb.set_prefix(prefix);
+    }
+    pb
+}
@@ -0,0 +1,33 @@
+//! 测试辅助
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// 测试用的临时目录，按名称及进程号区分，drop 时删除，断言失败时同样清理
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+
+    /// 创建空的临时目录，清除上次运行残留的内容
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("beelog-{}-test-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
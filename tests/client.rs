@@ -0,0 +1,67 @@
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use beelog::client::Client;
+use beelog::config::{ServerInfo, Timeouts};
+use beelog::error::{BeelogError, Result};
+use beelog::mfa::MfaProvider;
+
+/// 获取一个当前未监听的本地端口
+fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+struct FixedCode;
+
+impl MfaProvider for FixedCode {
+    fn code(&self) -> Result<String> {
+        Ok("123456".to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_builder_requires_server_and_nodes() {
+    let err = Client::builder().nodes(["node1"]).connect().await.err().unwrap();
+    assert!(matches!(err, BeelogError::Config(_)));
+
+    let server = ServerInfo::new("127.0.0.1", closed_port(), "ops");
+    let err = Client::builder().server(server).connect().await.err().unwrap();
+    assert!(matches!(err, BeelogError::Config(_)));
+}
+
+#[tokio::test]
+async fn test_builder_rejects_invalid_secret_code() {
+    let mut server = ServerInfo::new("127.0.0.1", closed_port(), "ops");
+    server.secret_code = Some("xxxx".to_string());
+    let err = Client::builder().server(server).nodes(["node1"]).connect().await.err().unwrap();
+    assert!(matches!(err, BeelogError::Decode(_)));
+}
+
+#[tokio::test]
+async fn test_connect_failures_are_reported_per_node() {
+    let server = ServerInfo::new("127.0.0.1", closed_port(), "ops");
+    let connected = Arc::new(Mutex::new(Vec::new()));
+    let record = connected.clone();
+    let client = Client::builder()
+        .server(server)
+        .nodes(["node1", "node2"])
+        .node_expecting("node3", "10.0.0.3")
+        .retries(0)
+        .timeouts(Timeouts { tcp: 2, ..Timeouts::default() })
+        .mfa_provider(Arc::new(FixedCode))
+        .on_connect(move |node, error| record.lock().unwrap().push((node.to_string(), error.is_some())))
+        .connect()
+        .await
+        .unwrap();
+
+    assert!(client.nodes().is_empty());
+    let failed: Vec<_> = client.failures().iter().map(|(node, _)| node.as_str()).collect();
+    assert_eq!(failed, ["node1", "node2", "node3"]);
+    assert!(client.failures().iter().all(|(_, e)| matches!(e, BeelogError::Connect(_))));
+    let mut connected = connected.lock().unwrap().clone();
+    connected.sort();
+    assert_eq!(connected, [("node1".to_string(), true), ("node2".to_string(), true), ("node3".to_string(), true)]);
+
+    assert!(client.exec_all("uptime").await.is_empty());
+    assert!(client.close().await.is_empty());
+}